@@ -42,18 +42,36 @@ pub fn is_webgl2_backend() -> bool {
 }
 
 /// Returns information about WebGL2 limitations
+///
+/// Once a device exists, storage buffer and compute support are taken from the
+/// adapter. For full details use `WDevice.getLimits()` / `getAdapterInfo()`.
 #[wasm_bindgen(js_name = getBackendLimitations)]
 pub fn get_backend_limitations() -> JsValue {
     let limitations = js_sys::Object::new();
 
+    // Storage buffers and compute shaders are never available on WebGL2,
+    // but report what the adapter says when we have one
+    let (storage_buffers, compute_shaders) = match wgpu_backend::try_get_device_state() {
+        Some(state) => {
+            let state = state.borrow();
+            let limits = state.adapter.limits();
+            let downlevel = state.adapter.get_downlevel_capabilities();
+            (
+                limits.max_storage_buffers_per_shader_stage > 0,
+                downlevel.flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+            )
+        }
+        None => (false, false),
+    };
+
     // Depth texture arrays are not well-supported on WebGL2
     let _ = js_sys::Reflect::set(&limitations, &"depthTextureArrays".into(), &false.into());
 
     // Storage buffers have limited support
-    let _ = js_sys::Reflect::set(&limitations, &"storageBuffers".into(), &false.into());
+    let _ = js_sys::Reflect::set(&limitations, &"storageBuffers".into(), &storage_buffers.into());
 
     // Compute shaders not available
-    let _ = js_sys::Reflect::set(&limitations, &"computeShaders".into(), &false.into());
+    let _ = js_sys::Reflect::set(&limitations, &"computeShaders".into(), &compute_shaders.into());
 
     limitations.into()
 }
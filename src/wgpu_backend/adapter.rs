@@ -0,0 +1,293 @@
+//! Adapter info, limits and features reporting
+//!
+//! Exposes what wgpu actually reports for the WebGL2 context so JS can pick
+//! quality tiers per device instead of relying on hard-coded assumptions.
+
+use wasm_bindgen::prelude::*;
+use super::device::{DeviceState, WDevice};
+use super::texture::WTextureFormat;
+use super::buffer::size_from_js;
+
+/// Convert a wgpu flag name to WebGPU style (e.g. `FLOAT32_FILTERABLE` -> `float32-filterable`)
+pub(crate) fn flag_name_to_js(name: &str) -> String {
+    name.to_ascii_lowercase().replace('_', "-")
+}
+
+/// Convert features to a JS array of WebGPU-style feature names
+pub(crate) fn features_to_js(features: wgpu::Features) -> JsValue {
+    let array = js_sys::Array::new();
+    for (name, _) in features.iter_names() {
+        array.push(&flag_name_to_js(name).into());
+    }
+    array.into()
+}
+
+/// Convert limits to a JS object using WebGPU's GPUSupportedLimits names
+pub(crate) fn limits_to_js(limits: &wgpu::Limits) -> JsValue {
+    let obj = js_sys::Object::new();
+    let set = |key: &str, value: f64| {
+        let _ = js_sys::Reflect::set(&obj, &key.into(), &value.into());
+    };
+
+    set("maxTextureDimension1D", limits.max_texture_dimension_1d as f64);
+    set("maxTextureDimension2D", limits.max_texture_dimension_2d as f64);
+    set("maxTextureDimension3D", limits.max_texture_dimension_3d as f64);
+    set("maxTextureArrayLayers", limits.max_texture_array_layers as f64);
+    set("maxBindGroups", limits.max_bind_groups as f64);
+    set("maxBindingsPerBindGroup", limits.max_bindings_per_bind_group as f64);
+    set("maxDynamicUniformBuffersPerPipelineLayout", limits.max_dynamic_uniform_buffers_per_pipeline_layout as f64);
+    set("maxDynamicStorageBuffersPerPipelineLayout", limits.max_dynamic_storage_buffers_per_pipeline_layout as f64);
+    set("maxSampledTexturesPerShaderStage", limits.max_sampled_textures_per_shader_stage as f64);
+    set("maxSamplersPerShaderStage", limits.max_samplers_per_shader_stage as f64);
+    set("maxStorageBuffersPerShaderStage", limits.max_storage_buffers_per_shader_stage as f64);
+    set("maxStorageTexturesPerShaderStage", limits.max_storage_textures_per_shader_stage as f64);
+    set("maxUniformBuffersPerShaderStage", limits.max_uniform_buffers_per_shader_stage as f64);
+    set("maxUniformBufferBindingSize", limits.max_uniform_buffer_binding_size as f64);
+    set("maxStorageBufferBindingSize", limits.max_storage_buffer_binding_size as f64);
+    set("minUniformBufferOffsetAlignment", limits.min_uniform_buffer_offset_alignment as f64);
+    set("minStorageBufferOffsetAlignment", limits.min_storage_buffer_offset_alignment as f64);
+    set("maxVertexBuffers", limits.max_vertex_buffers as f64);
+    set("maxBufferSize", limits.max_buffer_size as f64);
+    set("maxVertexAttributes", limits.max_vertex_attributes as f64);
+    set("maxVertexBufferArrayStride", limits.max_vertex_buffer_array_stride as f64);
+    set("maxInterStageShaderComponents", limits.max_inter_stage_shader_components as f64);
+    set("maxColorAttachments", limits.max_color_attachments as f64);
+    set("maxColorAttachmentBytesPerSample", limits.max_color_attachment_bytes_per_sample as f64);
+    set("maxComputeWorkgroupStorageSize", limits.max_compute_workgroup_storage_size as f64);
+    set("maxComputeInvocationsPerWorkgroup", limits.max_compute_invocations_per_workgroup as f64);
+    set("maxComputeWorkgroupSizeX", limits.max_compute_workgroup_size_x as f64);
+    set("maxComputeWorkgroupSizeY", limits.max_compute_workgroup_size_y as f64);
+    set("maxComputeWorkgroupSizeZ", limits.max_compute_workgroup_size_z as f64);
+    set("maxComputeWorkgroupsPerDimension", limits.max_compute_workgroups_per_dimension as f64);
+
+    obj.into()
+}
+
+/// Parse a WebGPU-style feature name (e.g. `float32-filterable`) into a wgpu feature
+pub(crate) fn feature_from_name(name: &str) -> Option<wgpu::Features> {
+    wgpu::Features::all()
+        .iter_names()
+        .find(|(flag_name, _)| flag_name_to_js(flag_name) == name)
+        .map(|(_, feature)| feature)
+}
+
+/// Set a limit by its WebGPU name
+///
+/// Fails if the name is unknown or the value is not a non-negative integer that
+/// fits the limit (u32, or a safe integer for the 64-bit `maxBufferSize`).
+pub(crate) fn set_limit(limits: &mut wgpu::Limits, name: &str, value: f64) -> Result<(), JsValue> {
+    let value = size_from_js(value, name)?;
+    if name == "maxBufferSize" {
+        limits.max_buffer_size = value;
+        return Ok(());
+    }
+    let v = u32::try_from(value)
+        .map_err(|_| JsValue::from_str(&format!("{} must be at most {}, got {}", name, u32::MAX, value)))?;
+    match name {
+        "maxTextureDimension1D" => limits.max_texture_dimension_1d = v,
+        "maxTextureDimension2D" => limits.max_texture_dimension_2d = v,
+        "maxTextureDimension3D" => limits.max_texture_dimension_3d = v,
+        "maxTextureArrayLayers" => limits.max_texture_array_layers = v,
+        "maxBindGroups" => limits.max_bind_groups = v,
+        "maxBindingsPerBindGroup" => limits.max_bindings_per_bind_group = v,
+        "maxDynamicUniformBuffersPerPipelineLayout" => limits.max_dynamic_uniform_buffers_per_pipeline_layout = v,
+        "maxDynamicStorageBuffersPerPipelineLayout" => limits.max_dynamic_storage_buffers_per_pipeline_layout = v,
+        "maxSampledTexturesPerShaderStage" => limits.max_sampled_textures_per_shader_stage = v,
+        "maxSamplersPerShaderStage" => limits.max_samplers_per_shader_stage = v,
+        "maxStorageBuffersPerShaderStage" => limits.max_storage_buffers_per_shader_stage = v,
+        "maxStorageTexturesPerShaderStage" => limits.max_storage_textures_per_shader_stage = v,
+        "maxUniformBuffersPerShaderStage" => limits.max_uniform_buffers_per_shader_stage = v,
+        "maxUniformBufferBindingSize" => limits.max_uniform_buffer_binding_size = v,
+        "maxStorageBufferBindingSize" => limits.max_storage_buffer_binding_size = v,
+        "minUniformBufferOffsetAlignment" => limits.min_uniform_buffer_offset_alignment = v,
+        "minStorageBufferOffsetAlignment" => limits.min_storage_buffer_offset_alignment = v,
+        "maxVertexBuffers" => limits.max_vertex_buffers = v,
+        "maxVertexAttributes" => limits.max_vertex_attributes = v,
+        "maxVertexBufferArrayStride" => limits.max_vertex_buffer_array_stride = v,
+        "maxInterStageShaderComponents" => limits.max_inter_stage_shader_components = v,
+        "maxColorAttachments" => limits.max_color_attachments = v,
+        "maxColorAttachmentBytesPerSample" => limits.max_color_attachment_bytes_per_sample = v,
+        "maxComputeWorkgroupStorageSize" => limits.max_compute_workgroup_storage_size = v,
+        "maxComputeInvocationsPerWorkgroup" => limits.max_compute_invocations_per_workgroup = v,
+        "maxComputeWorkgroupSizeX" => limits.max_compute_workgroup_size_x = v,
+        "maxComputeWorkgroupSizeY" => limits.max_compute_workgroup_size_y = v,
+        "maxComputeWorkgroupSizeZ" => limits.max_compute_workgroup_size_z = v,
+        "maxComputeWorkgroupsPerDimension" => limits.max_compute_workgroups_per_dimension = v,
+        _ => return Err(JsValue::from_str(&format!("Unknown limit '{}'", name))),
+    }
+    Ok(())
+}
+
+/// Format features of `format` on this WebGL2 context
+///
+/// The GL backend derives these from the context's extensions, e.g. float
+/// formats are only renderable with EXT_color_buffer_float (or
+/// EXT_color_buffer_half_float for 16-bit floats).
+pub(crate) fn texture_format_features(state: &DeviceState, format: WTextureFormat) -> wgpu::TextureFormatFeatures {
+    state.adapter.get_texture_format_features(format.to_wgpu())
+}
+
+/// Whether `format` can be sampled through a filtering sampler on this device
+///
+/// 32-bit float formats also need the `float32-filterable` feature enabled on
+/// the device (OES_texture_float_linear), whatever the adapter reports.
+pub(crate) fn is_format_filterable(state: &DeviceState, format: WTextureFormat) -> bool {
+    let filterable = texture_format_features(state, format)
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE);
+    let needs_float32_filterable = matches!(
+        format,
+        WTextureFormat::R32Float | WTextureFormat::Rg32Float | WTextureFormat::Rgba32Float
+    );
+    filterable && (!needs_float32_filterable || state.device.features().contains(wgpu::Features::FLOAT32_FILTERABLE))
+}
+
+/// Convert a format's capabilities on this device to a JS object
+fn format_capabilities_to_js(state: &DeviceState, format: WTextureFormat) -> JsValue {
+    let wgpu_format = format.to_wgpu();
+    let features = texture_format_features(state, format);
+    let flags = features.flags;
+    let usages = features.allowed_usages;
+    let (block_width, block_height) = wgpu_format.block_dimensions();
+
+    let sample_counts = js_sys::Array::new();
+    for count in flags.supported_sample_counts() {
+        sample_counts.push(&count.into());
+    }
+
+    let obj = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&obj, &key.into(), &value);
+    };
+
+    // Formats gated by a feature (depth32float-stencil8, compression) are unusable until it is enabled
+    set("supported", state.device.features().contains(wgpu_format.required_features()).into());
+    set("textureBinding", usages.contains(wgpu::TextureUsages::TEXTURE_BINDING).into());
+    set("renderable", usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT).into());
+    set("blendable", flags.contains(wgpu::TextureFormatFeatureFlags::BLENDABLE).into());
+    set("filterable", is_format_filterable(state, format).into());
+    set("multisampleable", flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X4).into());
+    set("resolvable", flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE).into());
+    set("sampleCounts", sample_counts.into());
+    set("storage", usages.contains(wgpu::TextureUsages::STORAGE_BINDING).into());
+    set("storageReadWrite", flags.contains(wgpu::TextureFormatFeatureFlags::STORAGE_READ_WRITE).into());
+    set("compressed", wgpu_format.is_compressed().into());
+    set("blockWidth", block_width.into());
+    set("blockHeight", block_height.into());
+    if let Some(size) = wgpu_format.block_copy_size(None) {
+        set("blockSize", size.into());
+    }
+
+    obj.into()
+}
+
+/// Convert adapter info and downlevel capabilities to a JS object
+fn adapter_info_to_js(info: &wgpu::AdapterInfo, downlevel: &wgpu::DownlevelCapabilities) -> JsValue {
+    let obj = js_sys::Object::new();
+
+    let device_type = match info.device_type {
+        wgpu::DeviceType::Other => "other",
+        wgpu::DeviceType::IntegratedGpu => "integrated-gpu",
+        wgpu::DeviceType::DiscreteGpu => "discrete-gpu",
+        wgpu::DeviceType::VirtualGpu => "virtual-gpu",
+        wgpu::DeviceType::Cpu => "cpu",
+    };
+
+    let shader_model = match downlevel.shader_model {
+        wgpu::ShaderModel::Sm2 => "sm2",
+        wgpu::ShaderModel::Sm4 => "sm4",
+        wgpu::ShaderModel::Sm5 => "sm5",
+    };
+
+    let downlevel_flags = js_sys::Array::new();
+    for (name, _) in downlevel.flags.iter_names() {
+        downlevel_flags.push(&flag_name_to_js(name).into());
+    }
+
+    let _ = js_sys::Reflect::set(&obj, &"name".into(), &info.name.as_str().into());
+    let _ = js_sys::Reflect::set(&obj, &"vendor".into(), &info.vendor.into());
+    let _ = js_sys::Reflect::set(&obj, &"device".into(), &info.device.into());
+    let _ = js_sys::Reflect::set(&obj, &"deviceType".into(), &device_type.into());
+    let _ = js_sys::Reflect::set(&obj, &"driver".into(), &info.driver.as_str().into());
+    let _ = js_sys::Reflect::set(&obj, &"driverInfo".into(), &info.driver_info.as_str().into());
+    let _ = js_sys::Reflect::set(&obj, &"backend".into(), &info.backend.to_str().into());
+    let _ = js_sys::Reflect::set(&obj, &"shaderModel".into(), &shader_model.into());
+    let _ = js_sys::Reflect::set(&obj, &"isWebGPUCompliant".into(), &downlevel.is_webgpu_compliant().into());
+    let _ = js_sys::Reflect::set(&obj, &"downlevelFlags".into(), &downlevel_flags.into());
+
+    obj.into()
+}
+
+#[wasm_bindgen]
+impl WDevice {
+    /// Get adapter info (name, vendor, driver, backend) and downlevel capabilities
+    #[wasm_bindgen(js_name = getAdapterInfo)]
+    pub fn get_adapter_info(&self) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        adapter_info_to_js(&state.adapter.get_info(), &state.adapter.get_downlevel_capabilities())
+    }
+
+    /// Get the limits supported by the adapter behind the WebGL2 context
+    #[wasm_bindgen(js_name = getLimits)]
+    pub fn get_limits(&self) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        limits_to_js(&state.adapter.limits())
+    }
+
+    /// Get the features supported by the adapter, as WebGPU-style names
+    #[wasm_bindgen(js_name = getFeatures)]
+    pub fn get_features(&self) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        features_to_js(state.adapter.features())
+    }
+
+    /// Whether `format` can be used as a render target on this WebGL2 context
+    #[wasm_bindgen(js_name = isTextureFormatRenderable)]
+    pub fn is_texture_format_renderable(&self, format: WTextureFormat) -> bool {
+        let state = self.state();
+        let state = state.borrow();
+        texture_format_features(&state, format)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    }
+
+    /// Whether `format` can be sampled with linear filtering on this WebGL2 context
+    #[wasm_bindgen(js_name = isTextureFormatFilterable)]
+    pub fn is_texture_format_filterable(&self, format: WTextureFormat) -> bool {
+        let state = self.state();
+        let state = state.borrow();
+        is_format_filterable(&state, format)
+    }
+
+    /// Get what `format` can be used for on this WebGL2 context
+    ///
+    /// Returns `{ supported, textureBinding, renderable, blendable, filterable,
+    /// multisampleable, resolvable, sampleCounts, storage, storageReadWrite,
+    /// compressed, blockWidth, blockHeight, blockSize }`. `blockSize` (bytes per
+    /// texel or block) is omitted for combined depth-stencil formats.
+    #[wasm_bindgen(js_name = getTextureFormatCapabilities)]
+    pub fn get_texture_format_capabilities(&self, format: WTextureFormat) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        format_capabilities_to_js(&state, format)
+    }
+
+    /// Get the limits the device was created with (see `WDeviceDescriptor`)
+    #[wasm_bindgen(js_name = getDeviceLimits)]
+    pub fn get_device_limits(&self) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        limits_to_js(&state.device.limits())
+    }
+
+    /// Get the features enabled on the device (see `WDeviceDescriptor`)
+    #[wasm_bindgen(js_name = getDeviceFeatures)]
+    pub fn get_device_features(&self) -> JsValue {
+        let state = self.state();
+        let state = state.borrow();
+        features_to_js(state.device.features())
+    }
+}
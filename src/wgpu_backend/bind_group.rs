@@ -2,11 +2,14 @@
 
 use wasm_bindgen::prelude::*;
 use super::device::WDevice;
-use super::buffer::WBuffer;
+use super::buffer::{size_from_js, WBuffer};
 use super::texture::WTextureView;
 use super::sampler::WSampler;
 use super::stats::{BIND_GROUP_COUNT, BIND_GROUP_LAYOUT_COUNT, PIPELINE_LAYOUT_COUNT};
+use super::error::capture_error;
+use super::lifetime::ResourceState;
 
+use std::rc::Rc;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 static BUILDER_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
@@ -16,6 +19,8 @@ static BUILDER_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
 pub struct WBindGroupBuilder {
     id: u32,
     entries: Vec<BindGroupBuilderEntry>,
+    // Buffers and textures referenced by the entries
+    resources: Vec<Rc<ResourceState>>,
 }
 
 struct BindGroupBuilderEntry {
@@ -42,12 +47,18 @@ impl WBindGroupBuilder {
         WBindGroupBuilder {
             id,
             entries: Vec::new(),
+            resources: Vec::new(),
         }
     }
 
     /// Add a buffer entry
+    ///
+    /// A `size` of 0 binds the rest of the buffer.
     #[wasm_bindgen(js_name = addBuffer)]
-    pub fn add_buffer(&mut self, binding: u32, buffer: &WBuffer, offset: u64, size: u64) {
+    pub fn add_buffer(&mut self, binding: u32, buffer: &WBuffer, offset: f64, size: f64) -> Result<(), JsValue> {
+        buffer.check_not_destroyed("addBuffer")?;
+        let offset = size_from_js(offset, "offset")?;
+        let size = size_from_js(size, "size")?;
         log::info!("Builder #{}: addBuffer binding={}, offset={}, size={}", self.id, binding, offset, size);
         self.entries.push(BindGroupBuilderEntry {
             binding,
@@ -57,6 +68,8 @@ impl WBindGroupBuilder {
                 size,
             },
         });
+        self.resources.push(buffer.lifetime.clone());
+        Ok(())
     }
 
     /// Add a sampler entry
@@ -72,6 +85,7 @@ impl WBindGroupBuilder {
     /// Add a texture view entry
     #[wasm_bindgen(js_name = addTextureView)]
     pub fn add_texture_view(&mut self, binding: u32, texture_view: &WTextureView) -> Result<(), JsValue> {
+        texture_view.check_not_destroyed("addTextureView")?;
         let view = texture_view
             .inner()
             .ok_or_else(|| JsValue::from_str("Cannot bind surface texture view"))?
@@ -81,12 +95,13 @@ impl WBindGroupBuilder {
             binding,
             entry_type: BindGroupEntryType::TextureView(view),
         });
+        self.resources.push(texture_view.texture_lifetime.clone());
         Ok(())
     }
 
     /// Build the bind group (consumes the builder)
     #[wasm_bindgen]
-    pub fn build(self, device: &WDevice, layout: &WBindGroupLayout) -> WBindGroup {
+    pub fn build(self, device: &WDevice, layout: &WBindGroupLayout) -> Result<WBindGroup, JsValue> {
         let state = device.state();
         let state = state.borrow();
 
@@ -132,15 +147,17 @@ impl WBindGroupBuilder {
 
         log::info!("Builder #{}: About to call device.create_bind_group with {} wgpu_entries", self.id, wgpu_entries.len());
 
-        let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &layout.inner,
-            entries: &wgpu_entries,
-        });
+        let bind_group = capture_error(&state.device, "createBindGroup", || {
+            state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout.inner,
+                entries: &wgpu_entries,
+            })
+        })?;
 
         log::debug!("Created bind group with {} entries", self.entries.len());
 
-        WBindGroup::new(bind_group)
+        Ok(WBindGroup::new(bind_group, self.resources))
     }
 }
 
@@ -208,6 +225,7 @@ impl WPipelineLayout {
 #[wasm_bindgen]
 pub struct WBindGroup {
     pub(crate) inner: wgpu::BindGroup,
+    pub(crate) resources: Vec<Rc<ResourceState>>,
 }
 
 impl WBindGroup {
@@ -215,9 +233,17 @@ impl WBindGroup {
         &self.inner
     }
 
-    pub(crate) fn new(inner: wgpu::BindGroup) -> Self {
+    pub(crate) fn new(inner: wgpu::BindGroup, resources: Vec<Rc<ResourceState>>) -> Self {
         BIND_GROUP_COUNT.fetch_add(1, Ordering::Relaxed);
-        Self { inner }
+        Self { inner, resources }
+    }
+
+    /// Fail with a clear error if a buffer or texture in the bind group has been destroyed
+    pub(crate) fn check_not_destroyed(&self, what: &str) -> Result<(), JsValue> {
+        if self.resources.iter().any(|resource| resource.is_destroyed()) {
+            return Err(JsValue::from_str(&format!("{}: bind group references a destroyed buffer or texture", what)));
+        }
+        Ok(())
     }
 }
 
@@ -377,12 +403,14 @@ pub fn create_bind_group_layout(
 
     let entry_count = entries.len() as u32;
 
-    let layout = state
-        .device
-        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &entries,
-        });
+    let layout = capture_error(&state.device, "createBindGroupLayout", || {
+        state
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &entries,
+            })
+    })?;
 
     log::debug!("Created bind group layout with {} entries", entry_count);
 
@@ -414,23 +442,25 @@ impl WPipelineLayoutBuilder {
 
     /// Build the pipeline layout
     #[wasm_bindgen]
-    pub fn build(self, device: &WDevice) -> WPipelineLayout {
+    pub fn build(self, device: &WDevice) -> Result<WPipelineLayout, JsValue> {
         let state = device.state();
         let state = state.borrow();
 
         let bind_group_layout_refs: Vec<&wgpu::BindGroupLayout> = self.layouts.iter().collect();
 
-        let layout = state
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &bind_group_layout_refs,
-                push_constant_ranges: &[],
-            });
+        let layout = capture_error(&state.device, "createPipelineLayout", || {
+            state
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &bind_group_layout_refs,
+                    push_constant_ranges: &[],
+                })
+        })?;
 
         log::info!("Created pipeline layout with {} bind group layouts", self.layouts.len());
 
-        WPipelineLayout::new(layout, self.layouts.len() as u32)
+        Ok(WPipelineLayout::new(layout, self.layouts.len() as u32))
     }
 }
 
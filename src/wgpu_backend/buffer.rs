@@ -1,9 +1,15 @@
 //! Buffer wrapper
 
 use wasm_bindgen::prelude::*;
-use super::device::{WDevice, WQueue};
+use super::device::{get_device_state, WDevice, WQueue};
 use super::stats::BUFFER_COUNT;
+use super::error::capture_error;
+use super::lifetime::{GpuResource, ResourceState};
+use super::queue_writes::{flush_writes_to, queue_buffer_write};
+use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 
 /// Buffer usage flags (matching WebGPU)
 pub mod buffer_usage {
@@ -19,12 +25,99 @@ pub mod buffer_usage {
     pub const QUERY_RESOLVE: u32 = 512;
 }
 
+/// Convert a JS number used as a buffer size or offset to u64
+///
+/// Like WebGPU, 64-bit sizes and offsets are plain numbers; they must be
+/// non-negative integers no larger than `Number.MAX_SAFE_INTEGER`.
+pub(crate) fn size_from_js(value: f64, what: &str) -> Result<u64, JsValue> {
+    const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+    if !(0.0..=MAX_SAFE_INTEGER).contains(&value) || value.fract() != 0.0 {
+        return Err(JsValue::from_str(&format!("{} must be a non-negative safe integer, got {}", what, value)));
+    }
+    Ok(value as u64)
+}
+
+/// Convert an optional JS number to u64 (see `size_from_js`)
+pub(crate) fn optional_size_from_js(value: Option<f64>, what: &str) -> Result<Option<u64>, JsValue> {
+    value.map(|v| size_from_js(v, what)).transpose()
+}
+
+/// Result recorded by a `map_async` callback (the callback must be Send)
+pub(crate) type MapResult = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+/// Poll the device until a `map_async` callback has recorded its result
+///
+/// A blocking poll can't be used on WebGL: wgpu's GL wait has a zero timeout
+/// there, and sync objects only change state between tasks. So the device is
+/// polled once per task, going back to the event loop (setTimeout) in between.
+pub(crate) async fn wait_for_map(device: wgpu::Device, result: MapResult) -> Result<(), wgpu::BufferAsyncError> {
+    loop {
+        if let Err(e) = device.poll(wgpu::PollType::Poll) {
+            log::warn!("Device poll failed while mapping buffer: {}", e);
+        }
+        if let Some(result) = result.lock().unwrap().take() {
+            return result;
+        }
+        next_task().await;
+    }
+}
+
+/// Resolve after the current task, letting the browser advance GL sync objects
+async fn next_task() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window()
+            .and_then(|window| window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 0).ok());
+        if scheduled.is_none() {
+            let _ = resolve.call0(&JsValue::UNDEFINED);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Map mode for `mapAsync` (matching WebGPU's GPUMapMode bits)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WMapMode {
+    Read = 1,
+    Write = 2,
+}
+
+impl WMapMode {
+    fn to_wgpu(self) -> wgpu::MapMode {
+        match self {
+            Self::Read => wgpu::MapMode::Read,
+            Self::Write => wgpu::MapMode::Write,
+        }
+    }
+
+    /// The buffer usage flag required to map with this mode
+    fn required_usage(self) -> u32 {
+        match self {
+            Self::Read => buffer_usage::MAP_READ,
+            Self::Write => buffer_usage::MAP_WRITE,
+        }
+    }
+}
+
+/// Mapping state of a buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MapState {
+    Unmapped,
+    Pending,
+    /// `wgpu_mapped` is false for an empty `mapAsync` range, which wgpu cannot map
+    Mapped { offset: u64, size: u64, writable: bool, wgpu_mapped: bool },
+}
+
 /// WebGPU Buffer wrapper
 #[wasm_bindgen]
 pub struct WBuffer {
     pub(crate) inner: wgpu::Buffer,
     pub(crate) size: u64,
     pub(crate) usage: u32,
+    // Shared with the pending mapAsync future, which outlives the borrow of self
+    map_state: Rc<Cell<MapState>>,
+    // Shared with bind groups and recorded commands referencing this buffer
+    pub(crate) lifetime: Rc<ResourceState>,
 }
 
 impl WBuffer {
@@ -34,7 +127,83 @@ impl WBuffer {
 
     pub(crate) fn new(inner: wgpu::Buffer, size: u64, usage: u32) -> Self {
         BUFFER_COUNT.fetch_add(1, Ordering::Relaxed);
-        Self { inner, size, usage }
+        Self {
+            inner,
+            size,
+            usage,
+            map_state: Rc::new(Cell::new(MapState::Unmapped)),
+            lifetime: ResourceState::new("buffer"),
+        }
+    }
+
+    /// Another wrapper around the same buffer, sharing its map and destroy state
+    pub(crate) fn share(&self) -> Self {
+        BUFFER_COUNT.fetch_add(1, Ordering::Relaxed);
+        Self {
+            inner: self.inner.clone(),
+            size: self.size,
+            usage: self.usage,
+            map_state: self.map_state.clone(),
+            lifetime: self.lifetime.clone(),
+        }
+    }
+
+    /// Fail with a clear error if the buffer has been destroyed
+    pub(crate) fn check_not_destroyed(&self, what: &str) -> Result<(), JsValue> {
+        self.lifetime.check_not_destroyed(what)
+    }
+
+    /// Validate a `writeBuffer` up front, since the write itself is deferred to submit
+    pub(crate) fn check_write(&self, offset: u64, len: usize) -> Result<(), JsValue> {
+        self.check_not_destroyed("writeBuffer")?;
+        if !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) || !(len as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+            return Err(JsValue::from_str(&format!(
+                "writeBuffer offset {} and size {} must be multiples of {}",
+                offset, len, wgpu::COPY_BUFFER_ALIGNMENT
+            )));
+        }
+        if offset + len as u64 > self.size {
+            return Err(JsValue::from_str(&format!(
+                "writeBuffer range [{}, {}) is out of bounds for buffer of size {}",
+                offset, offset + len as u64, self.size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Resolve an optional size to the rest of the buffer and check the range is in bounds
+    fn resolve_range(&self, offset: u64, size: Option<u64>) -> Result<u64, JsValue> {
+        let size = size.unwrap_or(self.size.saturating_sub(offset));
+        if offset.checked_add(size).is_none_or(|end| end > self.size) {
+            return Err(JsValue::from_str(&format!(
+                "Range [{}, {}) is out of bounds for buffer of size {}",
+                offset, offset + size, self.size
+            )));
+        }
+        Ok(size)
+    }
+
+    /// Check that `[offset, offset + size)` lies within the mapped range and widen it to
+    /// wgpu's mapping alignment, returning the aligned range and the start within it
+    ///
+    /// wgpu requires mapped views to start on MAP_ALIGNMENT and span a multiple of
+    /// COPY_BUFFER_ALIGNMENT; the mapped range itself always satisfies both.
+    fn aligned_mapped_range(&self, offset: u64, size: u64) -> Result<(std::ops::Range<u64>, usize), JsValue> {
+        let MapState::Mapped { offset: mapped_offset, size: mapped_size, .. } = self.map_state.get() else {
+            return Err(JsValue::from_str(&format!("Buffer is {}, not mapped", self.map_state())));
+        };
+
+        let mapped_end = mapped_offset + mapped_size;
+        if offset < mapped_offset || offset.checked_add(size).is_none_or(|end| end > mapped_end) {
+            return Err(JsValue::from_str(&format!(
+                "Range [{}, {}) is outside the mapped range [{}, {})",
+                offset, offset + size, mapped_offset, mapped_end
+            )));
+        }
+
+        let start = offset - (offset - mapped_offset) % wgpu::MAP_ALIGNMENT;
+        let end = (offset + size).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT).min(mapped_end);
+        Ok((start..end, (offset - start) as usize))
     }
 }
 
@@ -47,58 +216,257 @@ impl Drop for WBuffer {
 #[wasm_bindgen]
 impl WBuffer {
     #[wasm_bindgen(getter)]
-    pub fn size(&self) -> u32 {
-        self.size as u32
+    pub fn size(&self) -> f64 {
+        self.size as f64
+    }
+
+    /// Usage flags the buffer was created with (see `buffer_usage`)
+    #[wasm_bindgen(getter)]
+    pub fn usage(&self) -> u32 {
+        self.usage
+    }
+
+    /// Mapping state: "unmapped", "pending" or "mapped" (matching GPUBufferMapState)
+    #[wasm_bindgen(getter, js_name = mapState)]
+    pub fn map_state(&self) -> String {
+        match self.map_state.get() {
+            MapState::Unmapped => "unmapped",
+            MapState::Pending => "pending",
+            MapState::Mapped { .. } => "mapped",
+        }
+        .to_string()
+    }
+
+    /// Map a range of the buffer for reading or writing
+    ///
+    /// `offset` defaults to 0 and `size` to the rest of the buffer. `offset` must be a multiple of 8
+    /// and `size` a multiple of 4. The buffer must have the matching MAP_READ or
+    /// MAP_WRITE usage and must not be used in a submit while mapped. Any commands
+    /// writing to the buffer must already have been submitted. An empty range is
+    /// mapped immediately; `getMappedRange` then returns no bytes.
+    #[wasm_bindgen(js_name = mapAsync)]
+    pub fn map_async(&self, mode: WMapMode, offset: Option<f64>, size: Option<f64>) -> js_sys::Promise {
+        let range = self.check_not_destroyed("mapAsync").and_then(|()| {
+            let offset = optional_size_from_js(offset, "offset")?.unwrap_or(0);
+            let size = self.resolve_range(offset, optional_size_from_js(size, "size")?)?;
+            Ok((offset, size))
+        });
+        let (offset, size) = match range {
+            Ok(range) => range,
+            Err(e) => return js_sys::Promise::reject(&e),
+        };
+
+        let error = if self.usage & mode.required_usage() == 0 {
+            Some(format!("Buffer usage {:#x} does not allow mapping with {:?}", self.usage, mode))
+        } else if !offset.is_multiple_of(wgpu::MAP_ALIGNMENT) || !size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+            Some(format!(
+                "mapAsync offset {} must be a multiple of {} and size {} a multiple of {}",
+                offset, wgpu::MAP_ALIGNMENT, size, wgpu::COPY_BUFFER_ALIGNMENT
+            ))
+        } else if self.map_state.get() != MapState::Unmapped {
+            Some(format!("mapAsync called on a buffer that is already {}", self.map_state()))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return js_sys::Promise::reject(&JsValue::from_str(&error));
+        }
+
+        // wgpu rejects empty slices, and there is nothing to wait for
+        if size == 0 {
+            self.map_state.set(MapState::Mapped { offset, size, writable: mode == WMapMode::Write, wgpu_mapped: false });
+            return js_sys::Promise::resolve(&JsValue::UNDEFINED);
+        }
+
+        // Writes queued before mapping must land first
+        let device_state = get_device_state();
+        flush_writes_to(&device_state.borrow().queue, &self.inner);
+
+        // The callback must be Send, so it only records the result; the map
+        // state is updated once the future has seen it
+        let result: MapResult = Arc::new(Mutex::new(None));
+        let callback_result = result.clone();
+        self.inner.slice(offset..offset + size).map_async(mode.to_wgpu(), move |r| {
+            *callback_result.lock().unwrap() = Some(r);
+        });
+        self.map_state.set(MapState::Pending);
+
+        let device = device_state.borrow().device.clone();
+        let map_state = self.map_state.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            match wait_for_map(device, result).await {
+                Ok(()) => {
+                    map_state.set(MapState::Mapped { offset, size, writable: mode == WMapMode::Write, wgpu_mapped: true });
+                    log::debug!("Mapped buffer range [{}, {})", offset, offset + size);
+                    Ok(JsValue::UNDEFINED)
+                }
+                Err(e) => {
+                    map_state.set(MapState::Unmapped);
+                    Err(JsValue::from_str(&format!("mapAsync failed: {}", e)))
+                }
+            }
+        })
+    }
+
+    /// Get a copy of a mapped range of the buffer
+    ///
+    /// `offset` is absolute within the buffer and `size` defaults to the rest of
+    /// the mapped range. Both must lie within the range passed to `mapAsync`.
+    #[wasm_bindgen(js_name = getMappedRange)]
+    pub fn get_mapped_range(&self, offset: Option<f64>, size: Option<f64>) -> Result<Vec<u8>, JsValue> {
+        self.check_not_destroyed("getMappedRange")?;
+        let MapState::Mapped { offset: mapped_offset, size: mapped_size, .. } = self.map_state.get() else {
+            return Err(JsValue::from_str(&format!("getMappedRange called on a buffer that is {}", self.map_state())));
+        };
+
+        let offset = optional_size_from_js(offset, "offset")?.unwrap_or(mapped_offset);
+        let size = optional_size_from_js(size, "size")?.unwrap_or((mapped_offset + mapped_size).saturating_sub(offset));
+        let (range, start) = self.aligned_mapped_range(offset, size)?;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let view = self.inner.slice(range).get_mapped_range();
+        Ok(view[start..start + size as usize].to_vec())
+    }
+
+    /// Write bytes into the mapped buffer at `offset` (absolute within the buffer)
+    ///
+    /// The buffer must be mapped with `mappedAtCreation` or `mapAsync(Write)`. Chunks
+    /// can have any size and offset, so data can be streamed in without building the
+    /// whole content up front.
+    #[wasm_bindgen(js_name = writeMappedRange)]
+    pub fn write_mapped_range(&self, offset: f64, data: &[u8]) -> Result<(), JsValue> {
+        let offset = size_from_js(offset, "offset")?;
+        self.check_not_destroyed("writeMappedRange")?;
+        if let MapState::Mapped { writable: false, .. } = self.map_state.get() {
+            return Err(JsValue::from_str("writeMappedRange called on a buffer mapped for reading"));
+        }
+
+        let (range, start) = self.aligned_mapped_range(offset, data.len() as u64)?;
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut view = self.inner.slice(range).get_mapped_range_mut();
+        view[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Unmap the buffer, making it usable by the GPU again
+    ///
+    /// Unmapping a buffer with a pending `mapAsync` is an error.
+    pub fn unmap(&self) -> Result<(), JsValue> {
+        match self.map_state.get() {
+            MapState::Mapped { wgpu_mapped, .. } => {
+                if wgpu_mapped {
+                    self.inner.unmap();
+                }
+                self.map_state.set(MapState::Unmapped);
+                Ok(())
+            }
+            MapState::Unmapped => Ok(()),
+            MapState::Pending => Err(JsValue::from_str("unmap called while mapAsync is pending")),
+        }
+    }
+
+    /// Release the buffer's GPU memory
+    ///
+    /// The handle stays valid, but any later use of the buffer fails. A mapped
+    /// buffer is unmapped first. If recorded but unsubmitted commands still use
+    /// the buffer, the memory is released after they execute. Destroying twice is a no-op.
+    pub fn destroy(&self) {
+        if self.lifetime.is_destroyed() {
+            return;
+        }
+        if let MapState::Mapped { wgpu_mapped: true, .. } = self.map_state.get() {
+            self.inner.unmap();
+        }
+        // Recorded commands may read data from writes still waiting for submit
+        flush_writes_to(&get_device_state().borrow().queue, &self.inner);
+        self.map_state.set(MapState::Unmapped);
+        self.lifetime.destroy(GpuResource::Buffer(self.inner.clone()));
+        log::debug!("Destroyed buffer: size={}", self.size);
     }
 }
 
 /// Create a buffer
+///
+/// With `mapped_at_creation`, the buffer starts mapped for writing so it can be
+/// filled with `writeMappedRange` before `unmap()`. The size must then be a multiple of 4.
 #[wasm_bindgen(js_name = createBuffer)]
-pub fn create_buffer(device: &WDevice, size: u64, usage: u32) -> WBuffer {
+pub fn create_buffer(device: &WDevice, size: f64, usage: u32, mapped_at_creation: Option<bool>) -> Result<WBuffer, JsValue> {
+    let size = size_from_js(size, "size")?;
     let state = device.state();
     let state = state.borrow();
 
-    let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size,
-        usage: wgpu::BufferUsages::from_bits_truncate(usage),
-        mapped_at_creation: false,
-    });
+    let mapped_at_creation = mapped_at_creation.unwrap_or(false);
+    if mapped_at_creation && !size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+        return Err(JsValue::from_str(&format!(
+            "Buffers mapped at creation must have a size that is a multiple of {}, got {}",
+            wgpu::COPY_BUFFER_ALIGNMENT, size
+        )));
+    }
 
-    log::debug!("Created buffer: size={}, usage={:#x}", size, usage);
+    let buffer = capture_error(&state.device, "createBuffer", || {
+        state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::from_bits_truncate(usage),
+            mapped_at_creation,
+        })
+    })?;
 
-    WBuffer::new(buffer, size, usage)
+    log::debug!("Created buffer: size={}, usage={:#x}, mapped_at_creation={}", size, usage, mapped_at_creation);
+
+    let buffer = WBuffer::new(buffer, size, usage);
+    if mapped_at_creation {
+        buffer.map_state.set(MapState::Mapped { offset: 0, size, writable: true, wgpu_mapped: true });
+    }
+    Ok(buffer)
 }
 
 /// Create a buffer with initial data
+///
+/// The data length must be a multiple of 4. The buffer gets COPY_DST usage and
+/// is filled through the queue, so an invalid buffer reports a validation error
+/// (to an open error scope, if any) instead of failing to map.
 #[wasm_bindgen(js_name = createBufferWithData)]
-pub fn create_buffer_with_data(device: &WDevice, data: &[u8], usage: u32) -> WBuffer {
+pub fn create_buffer_with_data(device: &WDevice, data: &[u8], usage: u32) -> Result<WBuffer, JsValue> {
     let state = device.state();
     let state = state.borrow();
 
-    let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: data.len() as u64,
-        usage: wgpu::BufferUsages::from_bits_truncate(usage) | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: true,
-    });
+    let size = data.len() as u64;
+    if !size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+        return Err(JsValue::from_str(&format!(
+            "createBufferWithData data length must be a multiple of {}, got {}",
+            wgpu::COPY_BUFFER_ALIGNMENT, size
+        )));
+    }
 
-    // Write data to mapped buffer
-    buffer.slice(..).get_mapped_range_mut().copy_from_slice(data);
-    buffer.unmap();
+    let buffer = capture_error(&state.device, "createBufferWithData", || {
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::from_bits_truncate(usage) | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        state.queue.write_buffer(&buffer, 0, data);
+        buffer
+    })?;
 
-    log::debug!("Created buffer with data: size={}, usage={:#x}", data.len(), usage);
+    log::debug!("Created buffer with data: size={}, usage={:#x}", size, usage);
 
-    WBuffer::new(buffer, data.len() as u64, usage)
+    Ok(WBuffer::new(buffer, size, usage))
 }
 
-/// Write data to a buffer
+/// Write data to a buffer (applied at the next submit, see `WQueue::write_buffer`)
 #[wasm_bindgen(js_name = writeBuffer)]
-pub fn write_buffer(queue: &WQueue, buffer: &WBuffer, offset: u64, data: &[u8]) {
-    let state = queue.state();
-    let state = state.borrow();
-
-    state.queue.write_buffer(&buffer.inner, offset, data);
+pub fn write_buffer(_queue: &WQueue, buffer: &WBuffer, offset: f64, data: &[u8]) -> Result<(), JsValue> {
+    let offset = size_from_js(offset, "offset")?;
+    buffer.check_write(offset, data.len())?;
+    queue_buffer_write(&buffer.inner, offset, data);
 
-    log::debug!("Wrote {} bytes to buffer at offset {}", data.len(), offset);
+    log::debug!("Queued write of {} bytes to buffer at offset {}", data.len(), offset);
+    Ok(())
 }
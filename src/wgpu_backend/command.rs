@@ -4,16 +4,22 @@
 //! RenderPass borrows the CommandEncoder, which is difficult to expose through wasm-bindgen.
 //!
 //! Commands are recorded into a Vec and then executed when the pass ends.
+//! Each recorded pass keeps a `ResourceUse` for the buffers and textures it
+//! references, so destroying them before submit is deferred until after execution.
 
 use wasm_bindgen::prelude::*;
 use std::sync::atomic::Ordering;
 use super::device::{WDevice, get_device_state, DeviceState};
-use super::buffer::WBuffer;
+use super::buffer::{buffer_usage, optional_size_from_js, size_from_js, WBuffer};
 use super::pipeline::WRenderPipeline;
 use super::bind_group::WBindGroup;
-use super::texture::WTextureView;
+use super::texture::{WTexture, WTextureView};
+use super::mipmap::{mipmap_source, MipmapSource};
+use super::readback::{begin_surface_capture, SurfaceCapture};
 use super::types::*;
 use super::stats::{COMMAND_ENCODER_COUNT, RENDER_PASS_ENCODER_COUNT, COMMAND_BUFFER_COUNT};
+use super::error::{make_error_object, report_uncaptured_error};
+use super::lifetime::ResourceUse;
 use std::sync::Arc;
 use std::cell::RefCell;
 
@@ -24,6 +30,7 @@ enum RenderCommand {
     SetBindGroup {
         index: u32,
         bind_group: wgpu::BindGroup,
+        offsets: Vec<u32>,
     },
     SetVertexBuffer {
         slot: u32,
@@ -64,6 +71,23 @@ enum RenderCommand {
     },
 }
 
+/// Recorded encoder-level command, executed in recording order
+enum EncoderCommand {
+    RenderPass(Box<RenderPassConfig>, Vec<RenderCommand>),
+    ClearBuffer {
+        buffer: wgpu::Buffer,
+        offset: u64,
+        size: u64,
+        /// Keeps the buffer's destruction deferred until the clear has executed
+        resource: ResourceUse,
+    },
+    GenerateMipmaps {
+        texture: wgpu::Texture,
+        source: MipmapSource,
+        resource: ResourceUse,
+    },
+}
+
 /// Render pass configuration
 struct RenderPassConfig {
     /// Target texture view (None means surface texture)
@@ -78,6 +102,8 @@ struct RenderPassConfig {
     depth_load_op: wgpu::LoadOp<f32>,
     /// Whether to write depth
     depth_write: bool,
+    /// Buffers and textures used by the pass, keeping their destruction deferred
+    resources: Vec<ResourceUse>,
 }
 
 /// Command encoder
@@ -150,6 +176,7 @@ impl WCommandEncoder {
             color_load_op,
             depth_load_op: wgpu::LoadOp::Clear(1.0),
             depth_write: false,
+            resources: Vec::new(),
         };
 
         let encoder_index = self.render_passes.len();
@@ -173,7 +200,8 @@ impl WCommandEncoder {
         clear_b: f32,
         clear_a: f32,
         load_op: WLoadOp,
-    ) -> WRenderPassEncoder {
+    ) -> Result<WRenderPassEncoder, JsValue> {
+        color_view.check_not_destroyed("beginRenderPassWithView")?;
         log::debug!(
             "Begin render pass with view: is_surface={}, clear=({}, {}, {}, {})",
             color_view.is_surface_texture(),
@@ -199,24 +227,28 @@ impl WCommandEncoder {
             color_view.inner().cloned()
         };
 
-        let config = RenderPassConfig {
+        let mut config = RenderPassConfig {
             color_view: color_view_inner,
             depth_view: None,
             clear_color,
             color_load_op,
             depth_load_op: wgpu::LoadOp::Clear(1.0),
             depth_write: false,
+            resources: Vec::new(),
         };
+        if !color_view.is_surface_texture() {
+            config.resources.push(ResourceUse::new(&color_view.texture_lifetime));
+        }
 
         let encoder_index = self.render_passes.len();
 
         RENDER_PASS_ENCODER_COUNT.fetch_add(1, Ordering::Relaxed);
-        WRenderPassEncoder {
+        Ok(WRenderPassEncoder {
             device_state: self.device_state.clone(),
             config,
             commands: Vec::new(),
             encoder_index,
-        }
+        })
     }
 
     /// Begin a render pass with color and depth attachments
@@ -232,7 +264,9 @@ impl WCommandEncoder {
         load_op: WLoadOp,
         depth_clear_value: f32,
         depth_load_op: WLoadOp,
-    ) -> WRenderPassEncoder {
+    ) -> Result<WRenderPassEncoder, JsValue> {
+        color_view.check_not_destroyed("beginRenderPassWithDepth")?;
+        depth_view.check_not_destroyed("beginRenderPassWithDepth")?;
         log::debug!(
             "Begin render pass with depth: is_surface={}, clear=({}, {}, {}, {}), depth_clear={}",
             color_view.is_surface_texture(),
@@ -272,37 +306,109 @@ impl WCommandEncoder {
             depth_view_inner.is_some()
         );
 
-        let config = RenderPassConfig {
+        let mut config = RenderPassConfig {
             color_view: color_view_inner,
             depth_view: depth_view_inner,
             clear_color,
             color_load_op,
             depth_load_op: depth_load,
             depth_write: true,
+            resources: Vec::new(),
         };
+        if !color_view.is_surface_texture() {
+            config.resources.push(ResourceUse::new(&color_view.texture_lifetime));
+        }
+        config.resources.push(ResourceUse::new(&depth_view.texture_lifetime));
 
         let encoder_index = self.render_passes.len();
 
         RENDER_PASS_ENCODER_COUNT.fetch_add(1, Ordering::Relaxed);
-        WRenderPassEncoder {
+        Ok(WRenderPassEncoder {
             device_state: self.device_state.clone(),
             config,
             commands: Vec::new(),
             encoder_index,
+        })
+    }
+
+    /// Clear a range of a buffer to zero
+    ///
+    /// `offset` defaults to 0 and `size` to the rest of the buffer; both must be
+    /// multiples of 4 and the buffer needs COPY_DST usage. Executed in order with
+    /// the render passes recorded on this encoder.
+    #[wasm_bindgen(js_name = clearBuffer)]
+    pub fn clear_buffer(&mut self, buffer: &WBuffer, offset: Option<f64>, size: Option<f64>) -> Result<(), JsValue> {
+        buffer.check_not_destroyed("clearBuffer")?;
+        let offset = optional_size_from_js(offset, "offset")?.unwrap_or(0);
+        let size = optional_size_from_js(size, "size")?.unwrap_or(buffer.size.saturating_sub(offset));
+
+        let error = if buffer.usage & buffer_usage::COPY_DST == 0 {
+            Some(format!("clearBuffer requires COPY_DST usage, buffer usage is {:#x}", buffer.usage))
+        } else if !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) || !size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+            Some(format!(
+                "clearBuffer offset {} and size {} must be multiples of {}",
+                offset, size, wgpu::COPY_BUFFER_ALIGNMENT
+            ))
+        } else if offset.checked_add(size).is_none_or(|end| end > buffer.size) {
+            Some(format!(
+                "clearBuffer range [{}, {}) is out of bounds for buffer of size {}",
+                offset, offset + size, buffer.size
+            ))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return Err(JsValue::from_str(&error));
+        }
+
+        log::debug!("Recording: clear buffer range [{}, {})", offset, offset + size);
+        add_pending_command(EncoderCommand::ClearBuffer {
+            buffer: buffer.inner().clone(),
+            offset,
+            size,
+            resource: ResourceUse::new(&buffer.lifetime),
+        });
+        Ok(())
+    }
+
+    /// Fill mip levels 1.. of a texture from level 0
+    ///
+    /// Works on 2D, 2D array and cube textures with a renderable float format and
+    /// TEXTURE_BINDING | RENDER_ATTACHMENT usage; sRGB textures are filtered in
+    /// linear space. Executed in order with the render passes recorded on this
+    /// encoder, so level 0 can be rendered earlier in the same frame.
+    #[wasm_bindgen(js_name = generateMipmaps)]
+    pub fn generate_mipmaps(&mut self, texture: &WTexture) -> Result<(), JsValue> {
+        texture.check_not_destroyed("generateMipmaps")?;
+        let source = mipmap_source(&self.device_state.borrow(), texture).map_err(|e| JsValue::from_str(&e))?;
+        let Some(tex) = texture.inner() else {
+            return Ok(());
+        };
+        if tex.mip_level_count() < 2 {
+            log::debug!("generateMipmaps: texture has a single mip level, nothing to do");
+            return Ok(());
         }
+
+        log::debug!("Recording: generate {} mip levels ({:?})", tex.mip_level_count() - 1, source);
+        add_pending_command(EncoderCommand::GenerateMipmaps {
+            texture: tex.clone(),
+            source,
+            resource: ResourceUse::new(&texture.lifetime),
+        });
+        Ok(())
     }
 
     /// Finish the command encoder and retrieve all recorded passes
     pub fn finish(&mut self) -> WCommandBuffer {
-        // Get all pending passes from thread-local storage
-        let render_passes = take_pending_passes();
-        log::debug!("Finishing command encoder with {} render passes", render_passes.len());
+        // Get all pending commands from thread-local storage
+        let commands = take_pending_commands();
+        log::debug!("Finishing command encoder with {} commands", commands.len());
 
         // Track the real command buffer
         COMMAND_BUFFER_COUNT.fetch_add(1, Ordering::Relaxed);
         let cmd_buf = WCommandBuffer {
             device_state: self.device_state.clone(),
-            render_passes,
+            commands,
         };
 
         // Store for later submission by queue.submit()
@@ -312,7 +418,7 @@ impl WCommandEncoder {
         COMMAND_BUFFER_COUNT.fetch_add(1, Ordering::Relaxed);
         WCommandBuffer {
             device_state: self.device_state.clone(),
-            render_passes: Vec::new(),
+            commands: Vec::new(),
         }
     }
 }
@@ -321,7 +427,7 @@ impl WCommandEncoder {
 #[wasm_bindgen]
 pub struct WCommandBuffer {
     device_state: Arc<RefCell<DeviceState>>,
-    render_passes: Vec<(RenderPassConfig, Vec<RenderCommand>)>,
+    commands: Vec<EncoderCommand>,
 }
 
 impl Drop for WCommandBuffer {
@@ -333,27 +439,74 @@ impl Drop for WCommandBuffer {
 impl WCommandBuffer {
     /// Execute all recorded commands
     pub(crate) fn execute(&self) {
+        // Pick up any size change observed by auto resize before acquiring the frame
+        self.device_state.borrow_mut().apply_pending_resize();
+
         let state = self.device_state.borrow();
 
-        // Get surface texture for this frame
-        let surface_texture = match state.surface.get_current_texture() {
-            Ok(tex) => tex,
-            Err(e) => {
-                log::error!("Failed to get surface texture: {:?}", e);
-                return;
-            }
+        // Get surface texture for this frame (only if a pass renders to it, so
+        // offscreen passes still run when the surface can't be acquired)
+        let needs_surface = self.commands.iter().any(|command| {
+            matches!(command, EncoderCommand::RenderPass(config, _) if config.color_view.is_none())
+        });
+        let surface_texture = if needs_surface {
+            acquire_surface_texture(&state)
+        } else {
+            None
         };
 
-        let surface_view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let surface_view = surface_texture
+            .as_ref()
+            .map(|tex| tex.texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        // While a frame is being captured, surface passes render offscreen
+        let capture = surface_view.as_ref().and_then(|_| begin_surface_capture(&state));
+        let target_view = capture.as_ref().map(SurfaceCapture::view).or(surface_view.as_ref());
 
         // Create encoder and execute all passes
         let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("main encoder"),
         });
 
-        for (config, commands) in &self.render_passes {
+        for command in &self.commands {
+            let (config, commands) = match command {
+                EncoderCommand::RenderPass(config, commands) => (config, commands),
+                EncoderCommand::ClearBuffer { buffer, offset, size, resource } => {
+                    if let Some(kind) = resource.destroyed_kind() {
+                        let message = format!("clearBuffer uses a {} that was destroyed after the command was recorded", kind);
+                        log::warn!("{}", message);
+                        report_uncaptured_error(make_error_object("validation", &message));
+                    }
+                    encoder.clear_buffer(buffer, *offset, Some(*size));
+                    continue;
+                }
+                EncoderCommand::GenerateMipmaps { texture, source, resource } => {
+                    if let Some(kind) = resource.destroyed_kind() {
+                        let message = format!("generateMipmaps uses a {} that was destroyed after the command was recorded", kind);
+                        log::warn!("{}", message);
+                        report_uncaptured_error(make_error_object("validation", &message));
+                    }
+                    state.mipmaps.encode(&state.device, &mut encoder, texture, *source);
+                    continue;
+                }
+            };
+
             // Use surface view if no custom view provided
-            let color_view = config.color_view.as_ref().unwrap_or(&surface_view);
+            let color_view = match config.color_view.as_ref().or(target_view) {
+                Some(view) => view,
+                None => {
+                    log::warn!("Skipping render pass targeting the surface: no surface texture this frame");
+                    continue;
+                }
+            };
+
+            // Resources destroyed after recording are still alive (destruction is
+            // deferred), but the pass is invalid by WebGPU rules, so report it
+            if let Some(kind) = config.resources.iter().find_map(ResourceUse::destroyed_kind) {
+                let message = format!("Render pass uses a {} that was destroyed after the pass was recorded", kind);
+                log::warn!("{}", message);
+                report_uncaptured_error(make_error_object("validation", &message));
+            }
 
             log::info!(
                 "Executing render pass: has_color_view={}, has_depth_view={}, depth_write={}, commands={}",
@@ -400,8 +553,8 @@ impl WCommandBuffer {
                         RenderCommand::SetPipeline(pipeline) => {
                             render_pass.set_pipeline(pipeline);
                         }
-                        RenderCommand::SetBindGroup { index, bind_group } => {
-                            render_pass.set_bind_group(*index, bind_group, &[]);
+                        RenderCommand::SetBindGroup { index, bind_group, offsets } => {
+                            render_pass.set_bind_group(*index, bind_group, offsets);
                         }
                         RenderCommand::SetVertexBuffer { slot, buffer, offset } => {
                             render_pass.set_vertex_buffer(*slot, buffer.slice(*offset..));
@@ -444,33 +597,87 @@ impl WCommandBuffer {
             }
         }
 
+        let pending_capture = match (capture, surface_view.as_ref()) {
+            (Some(capture), Some(surface_view)) => Some(capture.finish(&state, &mut encoder, surface_view)),
+            _ => None,
+        };
+
         // Submit the command buffer
         state.queue.submit(std::iter::once(encoder.finish()));
 
+        if let Some(pending_capture) = pending_capture {
+            pending_capture.read_when_mapped(&state.device);
+        }
+
         // Present the surface
-        surface_texture.present();
+        if let Some(surface_texture) = surface_texture {
+            surface_texture.present();
+        }
 
-        log::debug!("Executed {} render passes and presented", self.render_passes.len());
+        log::debug!("Executed {} commands and presented", self.commands.len());
+    }
+}
+
+/// Acquire the surface texture for this frame, recovering from transient failures
+///
+/// Timeout is retried once; Outdated and Lost reconfigure the surface and retry.
+/// Anything that still fails is reported to JS through the uncaptured error callback
+/// as `{ type: "surface", reason, message }`.
+fn acquire_surface_texture(state: &DeviceState) -> Option<wgpu::SurfaceTexture> {
+    let error = match state.surface.get_current_texture() {
+        Ok(tex) => return Some(tex),
+        Err(e) => e,
+    };
+
+    let retry = match error {
+        wgpu::SurfaceError::Timeout => {
+            log::warn!("Surface texture acquisition timed out, retrying");
+            state.surface.get_current_texture()
+        }
+        wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost => {
+            log::warn!("Surface {:?}, reconfiguring and retrying", error);
+            state.surface.configure(&state.device, &state.surface_config);
+            state.surface.get_current_texture()
+        }
+        _ => Err(error),
+    };
+
+    match retry {
+        Ok(tex) => Some(tex),
+        Err(e) => {
+            log::error!("Failed to get surface texture: {:?}", e);
+            let reason = match e {
+                wgpu::SurfaceError::Timeout => "timeout",
+                wgpu::SurfaceError::Outdated => "outdated",
+                wgpu::SurfaceError::Lost => "lost",
+                wgpu::SurfaceError::OutOfMemory => "out-of-memory",
+                _ => "other",
+            };
+            let error = make_error_object("surface", &e.to_string());
+            let _ = js_sys::Reflect::set(&error, &"reason".into(), &reason.into());
+            report_uncaptured_error(error);
+            None
+        }
     }
 }
 
-// Thread-local storage for completed render passes
+// Thread-local storage for completed render passes and other encoder commands
 // This allows end() to store commands that finish() can retrieve
 thread_local! {
-    static PENDING_PASSES: RefCell<Vec<(RenderPassConfig, Vec<RenderCommand>)>> = const { RefCell::new(Vec::new()) };
+    static PENDING_COMMANDS: RefCell<Vec<EncoderCommand>> = const { RefCell::new(Vec::new()) };
     // The pending command buffer waiting to be submitted
     static PENDING_COMMAND_BUFFER: RefCell<Option<WCommandBuffer>> = const { RefCell::new(None) };
 }
 
-fn add_pending_pass(config: RenderPassConfig, commands: Vec<RenderCommand>) {
-    PENDING_PASSES.with(|passes| {
-        passes.borrow_mut().push((config, commands));
+fn add_pending_command(command: EncoderCommand) {
+    PENDING_COMMANDS.with(|commands| {
+        commands.borrow_mut().push(command);
     });
 }
 
-fn take_pending_passes() -> Vec<(RenderPassConfig, Vec<RenderCommand>)> {
-    PENDING_PASSES.with(|passes| {
-        std::mem::take(&mut *passes.borrow_mut())
+fn take_pending_commands() -> Vec<EncoderCommand> {
+    PENDING_COMMANDS.with(|commands| {
+        std::mem::take(&mut *commands.borrow_mut())
     })
 }
 
@@ -503,18 +710,25 @@ impl WRenderPassEncoder {
 
     /// Set a vertex buffer
     #[wasm_bindgen(js_name = setVertexBuffer)]
-    pub fn set_vertex_buffer(&mut self, slot: u32, buffer: &WBuffer, offset: u32) {
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer: &WBuffer, offset: f64) -> Result<(), JsValue> {
+        buffer.check_not_destroyed("setVertexBuffer")?;
+        let offset = size_from_js(offset, "offset")?;
         log::debug!("Recording: set vertex buffer at slot {}, offset {}", slot, offset);
+        self.config.resources.push(ResourceUse::new(&buffer.lifetime));
         self.commands.push(RenderCommand::SetVertexBuffer {
             slot,
             buffer: buffer.inner().clone(),
-            offset: offset as u64,
+            offset,
         });
+        Ok(())
     }
 
     /// Set the index buffer
     #[wasm_bindgen(js_name = setIndexBuffer)]
-    pub fn set_index_buffer(&mut self, buffer: &WBuffer, format: u32, offset: u32) {
+    pub fn set_index_buffer(&mut self, buffer: &WBuffer, format: u32, offset: f64) -> Result<(), JsValue> {
+        buffer.check_not_destroyed("setIndexBuffer")?;
+        let offset = size_from_js(offset, "offset")?;
+        self.config.resources.push(ResourceUse::new(&buffer.lifetime));
         log::debug!("Recording: set index buffer, format={}, offset={}", format, offset);
         let index_format = if format == 1 {
             wgpu::IndexFormat::Uint32
@@ -524,18 +738,26 @@ impl WRenderPassEncoder {
         self.commands.push(RenderCommand::SetIndexBuffer {
             buffer: buffer.inner().clone(),
             format: index_format,
-            offset: offset as u64,
+            offset,
         });
+        Ok(())
     }
 
     /// Set a bind group
+    ///
+    /// `dynamic_offsets` supplies one offset per binding with `hasDynamicOffset`,
+    /// in binding order (e.g. offsets returned by `WUniformRing.push`).
     #[wasm_bindgen(js_name = setBindGroup)]
-    pub fn set_bind_group(&mut self, group_index: u32, bind_group: &WBindGroup) {
+    pub fn set_bind_group(&mut self, group_index: u32, bind_group: &WBindGroup, dynamic_offsets: Option<Vec<u32>>) -> Result<(), JsValue> {
+        bind_group.check_not_destroyed("setBindGroup")?;
         log::debug!("Recording: set bind group at index {}", group_index);
+        self.config.resources.extend(bind_group.resources.iter().map(ResourceUse::new));
         self.commands.push(RenderCommand::SetBindGroup {
             index: group_index,
             bind_group: bind_group.inner().clone(),
+            offsets: dynamic_offsets.unwrap_or_default(),
         });
+        Ok(())
     }
 
     /// Draw primitives
@@ -618,6 +840,6 @@ impl WRenderPassEncoder {
         log::debug!("End render pass with {} commands", self.commands.len());
         RENDER_PASS_ENCODER_COUNT.fetch_sub(1, Ordering::Relaxed);
         // Store the completed pass in thread-local storage for finish() to retrieve
-        add_pending_pass(self.config, self.commands);
+        add_pending_command(EncoderCommand::RenderPass(Box::new(self.config), self.commands));
     }
 }
@@ -2,14 +2,79 @@
 
 use wasm_bindgen::prelude::*;
 use std::sync::Arc;
-use std::cell::RefCell;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use super::error::{capture_error, install_uncaptured_error_handler};
+use super::types::{WPresentMode, WCompositeAlphaMode};
+use super::adapter::{feature_from_name, set_limit};
+use super::mipmap::MipmapGenerator;
+use super::readback::SurfaceBlit;
+use super::stats::TEXTURE_COUNT;
+use std::sync::atomic::Ordering;
 
 /// Internal state shared between device operations
 pub(crate) struct DeviceState {
+    pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface: wgpu::Surface<'static>,
     pub surface_config: wgpu::SurfaceConfiguration,
+    /// How the canvas is composited with the page (fixed when the WebGL2 context is created)
+    pub alpha_mode: WCompositeAlphaMode,
+    pub canvas: web_sys::HtmlCanvasElement,
+    /// Set while automatic canvas resizing is enabled
+    pub auto_resize: Option<AutoResize>,
+    /// Pipelines for `generateMipmaps`, created on first use
+    pub mipmaps: MipmapGenerator,
+    /// Pipelines that draw a captured frame onto the surface (see `captureSurface`)
+    pub surface_blit: SurfaceBlit,
+}
+
+/// Automatic canvas resize state (see `WDevice.enableAutoResize`)
+pub(crate) struct AutoResize {
+    observer: web_sys::ResizeObserver,
+    _callback: Closure<dyn FnMut(js_sys::Array)>,
+    /// Size computed by the last ResizeObserver callback, applied at the start of the next frame
+    pending_size: Rc<Cell<Option<(u32, u32)>>>,
+}
+
+impl Drop for AutoResize {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+impl DeviceState {
+    /// Resize the surface, reconfiguring it only if the size actually changed
+    pub(crate) fn resize_surface(&mut self, width: u32, height: u32) {
+        if self.surface_config.width == width && self.surface_config.height == height {
+            return;
+        }
+
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+
+        // Reconfigure the surface with the new size (this also resizes the canvas)
+        self.surface.configure(&self.device, &self.surface_config);
+
+        log::info!("Viewport resized to {}x{} and surface reconfigured", width, height);
+    }
+
+    /// Apply the size observed by automatic resizing, if it changed since the last frame
+    pub(crate) fn apply_pending_resize(&mut self) {
+        let pending = self.auto_resize.as_ref().and_then(|auto_resize| auto_resize.pending_size.take());
+        if let Some((width, height)) = pending {
+            self.resize_surface(width, height);
+        }
+    }
+
+    /// The configured surface format as a WTextureFormat
+    pub(crate) fn surface_format(&self) -> WTextureFormat {
+        WTextureFormat::from_wgpu(self.surface_config.format).unwrap_or_else(|| {
+            log::warn!("Surface format {:?} has no WTextureFormat equivalent", self.surface_config.format);
+            WTextureFormat::Rgba8Unorm
+        })
+    }
 }
 
 // Thread-local storage for the current device state
@@ -24,6 +89,11 @@ pub(crate) fn get_device_state() -> Arc<RefCell<DeviceState>> {
     })
 }
 
+/// Like `get_device_state`, but returns None before `createDevice` has completed
+pub(crate) fn try_get_device_state() -> Option<Arc<RefCell<DeviceState>>> {
+    DEVICE_STATE.with(|state| state.borrow().clone())
+}
+
 fn set_device_state(state: Arc<RefCell<DeviceState>>) {
     DEVICE_STATE.with(|s| {
         *s.borrow_mut() = Some(state);
@@ -54,7 +124,8 @@ impl WQueue {
     }
 }
 
-use super::texture::{WTexture, WTextureFormat};
+use super::texture::{TextureUpload, WTexture, WTextureAspect, WTextureDimension, WTextureFormat, WTextureViewDimension};
+use super::lifetime::ResourceState;
 
 #[wasm_bindgen]
 impl WDevice {
@@ -69,24 +140,180 @@ impl WDevice {
     /// Get the current surface texture (default framebuffer)
     #[wasm_bindgen(js_name = getSurfaceTexture)]
     pub fn get_surface_texture(&self) -> WTexture {
-        let state = self.state.borrow();
+        let mut state = self.state.borrow_mut();
+        // Start of a new frame: pick up any size change observed by auto resize
+        state.apply_pending_resize();
+        TEXTURE_COUNT.fetch_add(1, Ordering::Relaxed);
         WTexture {
             inner: None,
             is_surface: true,
             width: state.surface_config.width,
             height: state.surface_config.height,
             depth_or_array_layers: 1,
-            format: WTextureFormat::Bgra8Unorm,
+            dimension: WTextureDimension::D2,
+            view_dimension: WTextureViewDimension::D2,
+            format: state.surface_format(),
             mip_level_count: 1,
+            lifetime: ResourceState::new("texture"),
+        }
+    }
+
+    /// Get the format the surface is configured with
+    #[wasm_bindgen(js_name = getSurfaceFormat)]
+    pub fn get_surface_format(&self) -> WTextureFormat {
+        self.state.borrow().surface_format()
+    }
+
+    /// Get the adapter's preferred canvas format (like navigator.gpu.getPreferredCanvasFormat)
+    #[wasm_bindgen(js_name = getPreferredCanvasFormat)]
+    pub fn get_preferred_canvas_format(&self) -> WTextureFormat {
+        let state = self.state.borrow();
+        let caps = state.surface.get_capabilities(&state.adapter);
+        // wgpu lists the preferred format first
+        caps.formats
+            .first()
+            .and_then(|format| WTextureFormat::from_wgpu(*format))
+            .unwrap_or(WTextureFormat::Rgba8Unorm)
+    }
+}
+
+/// Device descriptor (builder pattern) - required features and limits for `createDevice`
+///
+/// Without a descriptor the device gets no optional features and WebGL2 default limits.
+#[wasm_bindgen]
+pub struct WDeviceDescriptor {
+    required_features: Vec<String>,
+    required_limits: Vec<(String, f64)>,
+    best_limits: bool,
+    alpha_mode: Option<WCompositeAlphaMode>,
+}
+
+#[wasm_bindgen]
+impl WDeviceDescriptor {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WDeviceDescriptor {
+        WDeviceDescriptor {
+            required_features: Vec::new(),
+            required_limits: Vec::new(),
+            best_limits: false,
+            alpha_mode: None,
+        }
+    }
+
+    /// Require a feature by its WebGPU name (e.g. "float32-filterable", "texture-compression-bc")
+    #[wasm_bindgen(js_name = addRequiredFeature)]
+    pub fn add_required_feature(&mut self, name: &str) {
+        self.required_features.push(name.to_string());
+    }
+
+    /// Require a limit by its WebGPU name (e.g. "maxTextureDimension2D")
+    #[wasm_bindgen(js_name = setRequiredLimit)]
+    pub fn set_required_limit(&mut self, name: &str, value: f64) {
+        self.required_limits.push((name.to_string(), value));
+    }
+
+    /// Start from the best limits the adapter supports instead of WebGL2 defaults
+    #[wasm_bindgen(js_name = useBestLimits)]
+    pub fn use_best_limits(&mut self) {
+        self.best_limits = true;
+    }
+
+    /// Set how the canvas is composited with the page
+    ///
+    /// WebGL2 fixes this when the context is created, so it cannot be changed
+    /// later with `configureSurface`. Defaults to PreMultiplied (the browser default).
+    #[wasm_bindgen(js_name = setAlphaMode)]
+    pub fn set_alpha_mode(&mut self, alpha_mode: WCompositeAlphaMode) {
+        self.alpha_mode = Some(alpha_mode);
+    }
+}
+
+impl Default for WDeviceDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WDeviceDescriptor {
+    /// Resolve the requested features and limits against what the adapter supports
+    fn resolve(&self, adapter: &wgpu::Adapter) -> Result<(wgpu::Features, wgpu::Limits), JsValue> {
+        let adapter_features = adapter.features();
+        let adapter_limits = adapter.limits();
+
+        let mut features = wgpu::Features::empty();
+        let mut missing = Vec::new();
+        for name in &self.required_features {
+            let feature = feature_from_name(name)
+                .ok_or_else(|| JsValue::from_str(&format!("Unknown feature '{}'", name)))?;
+            if !adapter_features.contains(feature) {
+                missing.push(name.as_str());
+            }
+            features |= feature;
+        }
+        if !missing.is_empty() {
+            return Err(JsValue::from_str(&format!(
+                "Required feature(s) not supported by adapter: {}",
+                missing.join(", ")
+            )));
         }
+
+        let mut limits = if self.best_limits {
+            adapter_limits.clone()
+        } else {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        };
+        for (name, value) in &self.required_limits {
+            set_limit(&mut limits, name, *value)?;
+        }
+
+        let mut exceeded = Vec::new();
+        limits.check_limits_with_fail_fn(&adapter_limits, false, |name, requested, allowed| {
+            exceeded.push(format!("{} (requested {}, allowed {})", name, requested, allowed));
+        });
+        if !exceeded.is_empty() {
+            return Err(JsValue::from_str(&format!(
+                "Required limit(s) not supported by adapter: {}",
+                exceeded.join(", ")
+            )));
+        }
+
+        Ok((features, limits))
     }
 }
 
+/// Create the canvas' WebGL2 context with attributes matching the requested alpha mode
+fn create_webgl2_context(canvas: &web_sys::HtmlCanvasElement, alpha_mode: WCompositeAlphaMode) -> Result<(), JsValue> {
+    let (alpha, premultiplied_alpha) = match alpha_mode {
+        WCompositeAlphaMode::Opaque => (false, false),
+        WCompositeAlphaMode::PostMultiplied => (true, false),
+        _ => (true, true),
+    };
+
+    let options = js_sys::Object::new();
+    // Same as wgpu's own context options
+    let _ = js_sys::Reflect::set(&options, &"antialias".into(), &false.into());
+    let _ = js_sys::Reflect::set(&options, &"alpha".into(), &alpha.into());
+    let _ = js_sys::Reflect::set(&options, &"premultipliedAlpha".into(), &premultiplied_alpha.into());
+
+    canvas
+        .get_context_with_context_options("webgl2", &options)?
+        .ok_or_else(|| JsValue::from_str("Failed to create WebGL2 context"))?;
+
+    log::info!("Created WebGL2 context with alpha={}, premultipliedAlpha={}", alpha, premultiplied_alpha);
+    Ok(())
+}
+
 /// Create a device from a canvas element
 /// If requested_format is provided and supported, it will be used; otherwise falls back to a supported format
 /// If prefer_linear is true, prefers non-sRGB formats when falling back
+/// If descriptor is provided, its required features and limits are requested (failing if unsupported)
 #[wasm_bindgen(js_name = createDevice)]
-pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format: Option<WTextureFormat>, prefer_linear: Option<bool>) -> Result<WDevice, JsValue> {
+pub async fn create_device(
+    canvas: web_sys::HtmlCanvasElement,
+    requested_format: Option<WTextureFormat>,
+    prefer_linear: Option<bool>,
+    descriptor: Option<WDeviceDescriptor>,
+) -> Result<WDevice, JsValue> {
     let width = canvas.width();
     let height = canvas.height();
 
@@ -98,9 +325,19 @@ pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format:
         ..Default::default()
     });
 
-    // Create surface from canvas
+    // The canvas alpha mode can only be chosen when the WebGL2 context is created,
+    // so create it ourselves before wgpu does (getContext then returns this context)
+    let alpha_mode = match descriptor.as_ref().and_then(|d| d.alpha_mode) {
+        Some(WCompositeAlphaMode::Auto) | Some(WCompositeAlphaMode::Inherit) | None => WCompositeAlphaMode::PreMultiplied,
+        Some(alpha_mode) => {
+            create_webgl2_context(&canvas, alpha_mode)?;
+            alpha_mode
+        }
+    };
+
+    // Create surface from canvas (wgpu takes ownership, keep a handle for auto resize)
     let surface = instance
-        .create_surface(wgpu::SurfaceTarget::Canvas(canvas))
+        .create_surface(wgpu::SurfaceTarget::Canvas(canvas.clone()))
         .map_err(|e| JsValue::from_str(&format!("Failed to create surface: {:?}", e)))?;
 
     // Request adapter
@@ -115,12 +352,26 @@ pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format:
 
     log::info!("Got adapter: {:?}", adapter.get_info());
 
+    let (required_features, required_limits) = match descriptor {
+        Some(descriptor) => descriptor.resolve(&adapter)?,
+        None => (wgpu::Features::empty(), wgpu::Limits::downlevel_webgl2_defaults()),
+    };
+
+    // Compressed formats only need the WebGL extension, so enable whichever the context exposes
+    let compression_features = adapter.features()
+        & (wgpu::Features::TEXTURE_COMPRESSION_BC
+            | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+            | wgpu::Features::TEXTURE_COMPRESSION_ASTC);
+    let required_features = required_features | compression_features;
+
+    log::info!("Requesting device with features {:?}", required_features);
+
     // Request device
     let (device, queue) = adapter
         .request_device(&wgpu::DeviceDescriptor {
             label: Some("wgpu-webgl-wasm device"),
-            required_features: wgpu::Features::empty(),
-            required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
+            required_features,
+            required_limits,
             memory_hints: wgpu::MemoryHints::default(),
             trace: wgpu::Trace::default(),
             experimental_features: wgpu::ExperimentalFeatures::default(),
@@ -128,6 +379,9 @@ pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format:
         .await
         .map_err(|e| JsValue::from_str(&format!("Failed to get device: {:?}", e)))?;
 
+    // Report errors to JS instead of wgpu's default panic
+    install_uncaptured_error_handler(&device);
+
     // Configure surface
     let surface_caps = surface.get_capabilities(&adapter);
     let prefer_linear = prefer_linear.unwrap_or(false);
@@ -190,10 +444,16 @@ pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format:
     log::info!("Device created successfully with format {:?}", surface_format);
 
     let state = Arc::new(RefCell::new(DeviceState {
+        adapter,
         device,
         queue,
         surface,
         surface_config,
+        alpha_mode,
+        canvas,
+        auto_resize: None,
+        mipmaps: MipmapGenerator::default(),
+        surface_blit: SurfaceBlit::default(),
     }));
 
     set_device_state(state.clone());
@@ -201,45 +461,344 @@ pub async fn create_device(canvas: web_sys::HtmlCanvasElement, requested_format:
     Ok(WDevice { state })
 }
 
+/// Surface configuration (builder pattern) for `WDevice.configureSurface`
+///
+/// Anything not set keeps its current value.
+#[wasm_bindgen]
+pub struct WSurfaceConfiguration {
+    format: Option<WTextureFormat>,
+    present_mode: Option<WPresentMode>,
+    alpha_mode: Option<WCompositeAlphaMode>,
+    desired_maximum_frame_latency: Option<u32>,
+    view_formats: Option<Vec<WTextureFormat>>,
+}
+
+#[wasm_bindgen]
+impl WSurfaceConfiguration {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WSurfaceConfiguration {
+        WSurfaceConfiguration {
+            format: None,
+            present_mode: None,
+            alpha_mode: None,
+            desired_maximum_frame_latency: None,
+            view_formats: None,
+        }
+    }
+
+    #[wasm_bindgen(js_name = setFormat)]
+    pub fn set_format(&mut self, format: WTextureFormat) {
+        self.format = Some(format);
+    }
+
+    #[wasm_bindgen(js_name = setPresentMode)]
+    pub fn set_present_mode(&mut self, present_mode: WPresentMode) {
+        self.present_mode = Some(present_mode);
+    }
+
+    /// Must match the alpha mode the device was created with (see `WDeviceDescriptor.setAlphaMode`)
+    #[wasm_bindgen(js_name = setAlphaMode)]
+    pub fn set_alpha_mode(&mut self, alpha_mode: WCompositeAlphaMode) {
+        self.alpha_mode = Some(alpha_mode);
+    }
+
+    #[wasm_bindgen(js_name = setMaximumFrameLatency)]
+    pub fn set_maximum_frame_latency(&mut self, latency: u32) {
+        self.desired_maximum_frame_latency = Some(latency);
+    }
+
+    /// Add a format views of the surface texture may use (e.g. the sRGB variant)
+    #[wasm_bindgen(js_name = addViewFormat)]
+    pub fn add_view_format(&mut self, format: WTextureFormat) {
+        self.view_formats.get_or_insert_with(Vec::new).push(format);
+    }
+}
+
+impl Default for WSurfaceConfiguration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WDevice {
+    /// Reconfigure the surface (format, present mode, alpha mode, frame latency, view formats)
+    /// without recreating the device
+    #[wasm_bindgen(js_name = configureSurface)]
+    pub fn configure_surface(&self, config: &WSurfaceConfiguration) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        let caps = state.surface.get_capabilities(&state.adapter);
+
+        let mut surface_config = state.surface_config.clone();
+
+        if let Some(format) = config.format {
+            let format = format.to_wgpu();
+            if !caps.formats.contains(&format) {
+                return Err(JsValue::from_str(&format!(
+                    "Surface format {:?} not supported. Available formats: {:?}",
+                    format, caps.formats
+                )));
+            }
+            surface_config.format = format;
+        }
+
+        if let Some(present_mode) = config.present_mode {
+            let present_mode = present_mode.to_wgpu();
+            // Auto modes are resolved by wgpu and are always accepted
+            let is_auto = matches!(present_mode, wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync);
+            if !is_auto && !caps.present_modes.contains(&present_mode) {
+                return Err(JsValue::from_str(&format!(
+                    "Present mode {:?} not supported. Available present modes: {:?}",
+                    present_mode, caps.present_modes
+                )));
+            }
+            surface_config.present_mode = present_mode;
+        }
+
+        // wgpu always composites WebGL2 surfaces as Opaque; the real alpha mode
+        // comes from the context attributes chosen at device creation
+        if let Some(alpha_mode) = config.alpha_mode {
+            let keeps_current = matches!(alpha_mode, WCompositeAlphaMode::Auto | WCompositeAlphaMode::Inherit);
+            if !keeps_current && alpha_mode != state.alpha_mode {
+                return Err(JsValue::from_str(&format!(
+                    "Alpha mode {:?} requested but the WebGL2 context was created with {:?}. \
+                     Set it with WDeviceDescriptor.setAlphaMode when creating the device",
+                    alpha_mode, state.alpha_mode
+                )));
+            }
+        }
+
+        if let Some(latency) = config.desired_maximum_frame_latency {
+            surface_config.desired_maximum_frame_latency = latency;
+        }
+
+        if let Some(ref view_formats) = config.view_formats {
+            surface_config.view_formats = view_formats.iter().map(|f| f.to_wgpu()).collect();
+        }
+
+        capture_error(&state.device, "configureSurface", || {
+            state.surface.configure(&state.device, &surface_config)
+        })?;
+
+        log::info!(
+            "Surface reconfigured: format={:?}, present_mode={:?}, alpha_mode={:?}, latency={}, view_formats={:?}",
+            surface_config.format,
+            surface_config.present_mode,
+            surface_config.alpha_mode,
+            surface_config.desired_maximum_frame_latency,
+            surface_config.view_formats
+        );
+
+        state.surface_config = surface_config;
+        Ok(())
+    }
+
+    /// Get how the canvas is composited with the page
+    #[wasm_bindgen(js_name = getAlphaMode)]
+    pub fn get_alpha_mode(&self) -> WCompositeAlphaMode {
+        self.state.borrow().alpha_mode
+    }
+}
+
 /// Update the viewport size (called when canvas resizes)
 #[wasm_bindgen(js_name = setViewportSize)]
 pub fn set_viewport_size(device: &WDevice, width: u32, height: u32) {
-    let mut state = device.state.borrow_mut();
+    device.state.borrow_mut().resize_surface(width, height);
+}
 
-    // Only reconfigure if size actually changed
-    if state.surface_config.width == width && state.surface_config.height == height {
-        return;
+/// Compute the surface size for a canvas from a ResizeObserver entry
+///
+/// Uses devicePixelContentBoxSize when available (exact device pixels), otherwise
+/// contentBoxSize * devicePixelRatio. The result is scaled by `dpr_scale` and clamped
+/// to `max_width`/`max_height` (0 = no limit), preserving aspect ratio.
+fn observed_surface_size(
+    entry: &JsValue,
+    use_device_pixels: bool,
+    dpr_scale: f64,
+    max_width: u32,
+    max_height: u32,
+) -> Option<(u32, u32)> {
+    let (box_name, pixel_ratio) = if use_device_pixels {
+        ("devicePixelContentBoxSize", 1.0)
+    } else {
+        ("contentBoxSize", web_sys::window()?.device_pixel_ratio())
+    };
+
+    // Older browsers report a single size object instead of an array
+    let sizes = js_sys::Reflect::get(entry, &box_name.into()).ok()?;
+    let size = if js_sys::Array::is_array(&sizes) {
+        js_sys::Array::from(&sizes).get(0)
+    } else {
+        sizes
+    };
+    let inline_size = js_sys::Reflect::get(&size, &"inlineSize".into()).ok()?.as_f64()?;
+    let block_size = js_sys::Reflect::get(&size, &"blockSize".into()).ok()?.as_f64()?;
+
+    let mut width = inline_size * pixel_ratio * dpr_scale;
+    let mut height = block_size * pixel_ratio * dpr_scale;
+
+    let mut clamp = 1.0f64;
+    if max_width > 0 && width > max_width as f64 {
+        clamp = clamp.min(max_width as f64 / width);
     }
+    if max_height > 0 && height > max_height as f64 {
+        clamp = clamp.min(max_height as f64 / height);
+    }
+    width *= clamp;
+    height *= clamp;
+
+    Some(((width.round() as u32).max(1), (height.round() as u32).max(1)))
+}
+
+/// Whether ResizeObserver can report device-pixel sizes (not supported in Safari)
+fn supports_device_pixel_content_box() -> bool {
+    js_sys::Reflect::get(&js_sys::global(), &"ResizeObserverEntry".into())
+        .and_then(|entry| js_sys::Reflect::get(&entry, &"prototype".into()))
+        .and_then(|prototype| js_sys::Reflect::has(&prototype, &"devicePixelContentBoxSize".into()))
+        .unwrap_or(false)
+}
 
-    state.surface_config.width = width;
-    state.surface_config.height = height;
+#[wasm_bindgen]
+impl WDevice {
+    /// Automatically resize the surface to follow the canvas' displayed size
+    ///
+    /// A ResizeObserver watches the canvas; the surface is reconfigured at the start
+    /// of the next frame. `dpr_scale` scales the device pixel size (e.g. 0.5 renders at
+    /// half resolution), `max_width`/`max_height` cap the resolution (0 = no limit).
+    /// Replaces `setViewportSize` calls from JS.
+    #[wasm_bindgen(js_name = enableAutoResize)]
+    pub fn enable_auto_resize(&self, dpr_scale: f64, max_width: u32, max_height: u32) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+
+        // Never exceed what a texture can hold
+        let max_dimension = state.device.limits().max_texture_dimension_2d;
+        let max_width = if max_width == 0 { max_dimension } else { max_width.min(max_dimension) };
+        let max_height = if max_height == 0 { max_dimension } else { max_height.min(max_dimension) };
+        let dpr_scale = if dpr_scale > 0.0 { dpr_scale } else { 1.0 };
+
+        let use_device_pixels = supports_device_pixel_content_box();
+        let pending_size = Rc::new(Cell::new(None));
+
+        let callback_pending_size = pending_size.clone();
+        let callback = Closure::<dyn FnMut(js_sys::Array)>::new(move |entries: js_sys::Array| {
+            // Only the last entry matters, we observe a single canvas
+            let entry = entries.get(entries.length().saturating_sub(1));
+            if let Some(size) = observed_surface_size(&entry, use_device_pixels, dpr_scale, max_width, max_height) {
+                log::debug!("Canvas resized, surface will be {}x{} on the next frame", size.0, size.1);
+                callback_pending_size.set(Some(size));
+            }
+        });
+
+        let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref())?;
+        let options = web_sys::ResizeObserverOptions::new();
+        options.set_box(if use_device_pixels {
+            web_sys::ResizeObserverBoxOptions::DevicePixelContentBox
+        } else {
+            web_sys::ResizeObserverBoxOptions::ContentBox
+        });
+        observer.observe_with_options(&state.canvas, &options);
+
+        log::info!(
+            "Auto resize enabled: device_pixels={}, dpr_scale={}, max={}x{}",
+            use_device_pixels, dpr_scale, max_width, max_height
+        );
+
+        // Replacing a previous AutoResize disconnects its observer
+        state.auto_resize = Some(AutoResize {
+            observer,
+            _callback: callback,
+            pending_size,
+        });
+
+        Ok(())
+    }
 
-    // Reconfigure the surface with the new size
-    state.surface.configure(&state.device, &state.surface_config);
+    /// Stop following the canvas size (JS is back to calling `setViewportSize`)
+    #[wasm_bindgen(js_name = disableAutoResize)]
+    pub fn disable_auto_resize(&self) {
+        self.state.borrow_mut().auto_resize = None;
+    }
 
-    log::info!("Viewport resized to {}x{} and surface reconfigured", width, height);
+    /// Get the current surface size in pixels as `{ width, height }`
+    #[wasm_bindgen(js_name = getSurfaceSize)]
+    pub fn get_surface_size(&self) -> JsValue {
+        let state = self.state.borrow();
+        let size = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&size, &"width".into(), &state.surface_config.width.into());
+        let _ = js_sys::Reflect::set(&size, &"height".into(), &state.surface_config.height.into());
+        size.into()
+    }
 }
 
-use super::buffer::WBuffer;
+use super::buffer::{optional_size_from_js, size_from_js, WBuffer};
 use super::command::execute_pending_command_buffer;
+use super::ring::flush_uniform_rings;
+use super::queue_writes::{flush_buffer_writes, queue_buffer_write};
+use super::types::WPredefinedColorSpace;
+
+/// Identify a DOM image source for `copyExternalImageToTexture`
+fn external_image_source(value: &JsValue) -> Result<wgpu::ExternalImageSource, JsValue> {
+    if let Some(bitmap) = value.dyn_ref::<web_sys::ImageBitmap>() {
+        Ok(wgpu::ExternalImageSource::ImageBitmap(bitmap.clone()))
+    } else if let Some(image) = value.dyn_ref::<web_sys::HtmlImageElement>() {
+        Ok(wgpu::ExternalImageSource::HTMLImageElement(image.clone()))
+    } else if let Some(video) = value.dyn_ref::<web_sys::HtmlVideoElement>() {
+        Ok(wgpu::ExternalImageSource::HTMLVideoElement(video.clone()))
+    } else if let Some(canvas) = value.dyn_ref::<web_sys::HtmlCanvasElement>() {
+        Ok(wgpu::ExternalImageSource::HTMLCanvasElement(canvas.clone()))
+    } else if let Some(canvas) = value.dyn_ref::<web_sys::OffscreenCanvas>() {
+        Ok(wgpu::ExternalImageSource::OffscreenCanvas(canvas.clone()))
+    } else if let Some(image_data) = value.dyn_ref::<web_sys::ImageData>() {
+        Ok(wgpu::ExternalImageSource::ImageData(image_data.clone()))
+    } else {
+        Err(JsValue::from_str(
+            "copyExternalImageToTexture: source must be an ImageBitmap, HTMLImageElement, HTMLVideoElement, HTMLCanvasElement, OffscreenCanvas or ImageData",
+        ))
+    }
+}
 
 #[wasm_bindgen]
 impl WQueue {
     /// Submit command buffers - executes all recorded commands and presents the surface
     pub fn submit(&self) {
         log::debug!("Queue submit - executing pending command buffer");
+        // Upload coalesced writes and per-frame uniform data before the commands that use them
+        {
+            let state = self.state.borrow();
+            flush_buffer_writes(&state.queue);
+            flush_uniform_rings(&state.queue);
+        }
         execute_pending_command_buffer();
     }
 
     /// Write data to a buffer
+    ///
+    /// The write is applied at the next `submit()`, merged with other writes to
+    /// the same buffer (see `getQueueWriteStats`).
     #[wasm_bindgen(js_name = writeBuffer)]
-    pub fn write_buffer(&self, buffer: &WBuffer, offset: u32, data: &[u8]) {
-        let state = self.state.borrow();
-        state.queue.write_buffer(buffer.inner(), offset as u64, data);
-        log::debug!("Wrote {} bytes to buffer at offset {}", data.len(), offset);
+    pub fn write_buffer(&self, buffer: &WBuffer, offset: f64, data: &[u8]) -> Result<(), JsValue> {
+        let offset = size_from_js(offset, "offset")?;
+        buffer.check_write(offset, data.len())?;
+        queue_buffer_write(buffer.inner(), offset, data);
+        log::debug!("Queued write of {} bytes to buffer at offset {}", data.len(), offset);
+        Ok(())
     }
 
-    /// Write data to a texture
+    /// Write data to a region of a texture
+    ///
+    /// (`origin_x`, `origin_y`, `origin_z`) and `width` x `height` x `depth` select
+    /// the region of `mip_level`; for 2D arrays and cubes z is the layer. The region
+    /// starts at byte `data_offset` (default 0) of `data`, with `bytes_per_row`
+    /// between rows and `rows_per_image` rows between layers or slices (0 for
+    /// either means tightly packed). For compressed formats rows are rows of
+    /// blocks, and the region must be block-aligned except where it reaches the
+    /// mip edge. `aspect` (default All) selects the depth or stencil of a
+    /// depth-stencil texture.
+    ///
+    /// `conversion` is a set of flags from `getUploadConversion()` (RGB_TO_RGBA,
+    /// SWAP_RED_BLUE, PREMULTIPLY_ALPHA, FLIP_Y) applied on the CPU before the
+    /// upload, for 8-bit RGBA/BGRA textures. With RGB_TO_RGBA the source layout
+    /// is in 3-byte texels.
     #[wasm_bindgen(js_name = writeTexture)]
     pub fn write_texture(
         &self,
@@ -250,40 +809,97 @@ impl WQueue {
         origin_z: u32,
         data: &[u8],
         bytes_per_row: u32,
-        _rows_per_image: u32,
+        rows_per_image: u32,
         width: u32,
         height: u32,
         depth: u32,
-    ) {
+        aspect: Option<WTextureAspect>,
+        data_offset: Option<f64>,
+        conversion: Option<u32>,
+    ) -> Result<(), JsValue> {
+        let offset = optional_size_from_js(data_offset, "data_offset")?.unwrap_or(0);
         let state = self.state.borrow();
+        texture.upload(&state, data, &TextureUpload {
+            mip_level,
+            origin: wgpu::Origin3d {
+                x: origin_x,
+                y: origin_y,
+                z: origin_z,
+            },
+            aspect: aspect.unwrap_or(WTextureAspect::All),
+            offset,
+            bytes_per_row,
+            rows_per_image,
+            width,
+            height,
+            depth,
+            conversion: conversion.unwrap_or(0),
+        })
+    }
+
+    /// Copy an image, canvas or video frame into a texture at (`origin_x`, `origin_y`, `origin_z`)
+    ///
+    /// `source` is an ImageBitmap, HTMLImageElement, HTMLVideoElement,
+    /// HTMLCanvasElement, OffscreenCanvas or ImageData; the whole source is copied.
+    /// The browser uploads it directly (texImage2D), so nothing is decoded or
+    /// copied on the CPU. `color_space` defaults to sRGB. `mip_level` and
+    /// `origin_z` default to 0; for 2D arrays and cubes z is the layer (cube faces
+    /// are layers 0-5), for 3D textures the depth slice.
+    #[wasm_bindgen(js_name = copyExternalImageToTexture)]
+    pub fn copy_external_image_to_texture(
+        &self,
+        source: &JsValue,
+        texture: &WTexture,
+        origin_x: u32,
+        origin_y: u32,
+        flip_y: bool,
+        premultiply_alpha: bool,
+        color_space: Option<WPredefinedColorSpace>,
+        mip_level: Option<u32>,
+        origin_z: Option<u32>,
+    ) -> Result<(), JsValue> {
+        texture.check_not_destroyed("copyExternalImageToTexture")?;
+        let Some(tex) = texture.inner() else {
+            return Err(JsValue::from_str("copyExternalImageToTexture: cannot copy to the surface texture"));
+        };
+        let source = external_image_source(source)?;
+        let (width, height) = (source.width(), source.height());
+        if width == 0 || height == 0 {
+            return Err(JsValue::from_str("copyExternalImageToTexture: source has no pixels (not loaded yet?)"));
+        }
 
-        if let Some(ref tex) = texture.inner {
-            state.queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
+        let state = self.state.borrow();
+        capture_error(&state.device, "copyExternalImageToTexture", || {
+            state.queue.copy_external_image_to_texture(
+                &wgpu::CopyExternalImageSourceInfo {
+                    source,
+                    origin: wgpu::Origin2d::ZERO,
+                    flip_y,
+                },
+                wgpu::CopyExternalImageDestInfo {
                     texture: tex,
-                    mip_level,
+                    mip_level: mip_level.unwrap_or(0),
                     origin: wgpu::Origin3d {
                         x: origin_x,
                         y: origin_y,
-                        z: origin_z,
+                        z: origin_z.unwrap_or(0),
                     },
                     aspect: wgpu::TextureAspect::All,
-                },
-                data,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(bytes_per_row),
-                    rows_per_image: None,
+                    color_space: color_space.unwrap_or(WPredefinedColorSpace::Srgb).to_wgpu(),
+                    premultiplied_alpha: premultiply_alpha,
                 },
                 wgpu::Extent3d {
                     width,
                     height,
-                    depth_or_array_layers: depth,
+                    depth_or_array_layers: 1,
                 },
-            );
-            log::debug!("Wrote texture data: {}x{}x{} at mip {}", width, height, depth, mip_level);
-        } else {
-            log::warn!("Cannot write to surface texture");
-        }
+            )
+        })?;
+
+        log::debug!(
+            "Copied {}x{} external image to mip {} of texture at ({}, {}, {})",
+            width, height, mip_level.unwrap_or(0), origin_x, origin_y, origin_z.unwrap_or(0)
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,162 @@
+//! Error scopes and uncaptured error reporting
+//!
+//! wgpu's default behaviour is to panic on any error that is not caught by an
+//! error scope. We install an uncaptured error handler at device creation that
+//! forwards errors to an optional JS callback instead, and expose
+//! `pushErrorScope`/`popErrorScope` so JS can attribute errors to the calls
+//! that caused them, like on native WebGPU.
+
+use wasm_bindgen::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use super::device::WDevice;
+
+/// Error filter for error scopes (matching WebGPU's GPUErrorFilter)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WErrorFilter {
+    Validation = 0,
+    OutOfMemory = 1,
+    Internal = 2,
+}
+
+impl WErrorFilter {
+    pub(crate) fn to_wgpu(self) -> wgpu::ErrorFilter {
+        match self {
+            Self::Validation => wgpu::ErrorFilter::Validation,
+            Self::OutOfMemory => wgpu::ErrorFilter::OutOfMemory,
+            Self::Internal => wgpu::ErrorFilter::Internal,
+        }
+    }
+}
+
+// Since WASM is single-threaded, the JS callback and the scope depth live in
+// thread-local storage (the uncaptured error handler must be Send + Sync).
+thread_local! {
+    static UNCAPTURED_ERROR_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    // Number of error scopes currently pushed from JS
+    static ERROR_SCOPE_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Convert a wgpu error into a JS object `{ type, message }`
+///
+/// `type` is one of "validation", "out-of-memory" or "internal".
+pub(crate) fn error_to_js(error: &wgpu::Error) -> JsValue {
+    let error_type = match error {
+        wgpu::Error::Validation { .. } => "validation",
+        wgpu::Error::OutOfMemory { .. } => "out-of-memory",
+        wgpu::Error::Internal { .. } => "internal",
+    };
+    make_error_object(error_type, &error.to_string())
+}
+
+/// Build a JS error object `{ type, message }`
+pub(crate) fn make_error_object(error_type: &str, message: &str) -> JsValue {
+    let obj = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&obj, &"type".into(), &error_type.into());
+    let _ = js_sys::Reflect::set(&obj, &"message".into(), &message.into());
+    obj.into()
+}
+
+/// Forward an error object to the JS uncaptured error callback, or log it if none is set
+pub(crate) fn report_uncaptured_error(error: JsValue) {
+    let callback = UNCAPTURED_ERROR_CALLBACK.with(|cb| cb.borrow().clone());
+    match callback {
+        Some(callback) => {
+            if let Err(e) = callback.call1(&JsValue::NULL, &error) {
+                log::error!("onUncapturedError callback threw: {:?}", e);
+            }
+        }
+        None => log::error!("Uncaptured wgpu error: {:?}", error),
+    }
+}
+
+/// Install the uncaptured error handler on a freshly created device
+pub(crate) fn install_uncaptured_error_handler(device: &wgpu::Device) {
+    device.on_uncaptured_error(std::sync::Arc::new(|error: wgpu::Error| {
+        report_uncaptured_error(error_to_js(&error));
+    }));
+}
+
+/// Run a resource creation call and turn any validation or out-of-memory error into `Err`
+///
+/// If JS has an error scope open, the error belongs to that scope (as on native
+/// WebGPU), so the call is run as-is and always returns `Ok`.
+pub(crate) fn capture_error<T>(device: &wgpu::Device, what: &str, f: impl FnOnce() -> T) -> Result<T, JsValue> {
+    if ERROR_SCOPE_DEPTH.with(|depth| depth.get()) > 0 {
+        return Ok(f());
+    }
+
+    device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = f();
+    let validation_error = poll_now(device.pop_error_scope());
+    let oom_error = poll_now(device.pop_error_scope());
+
+    match validation_error.or(oom_error) {
+        Some(error) => Err(JsValue::from_str(&format!("{} failed: {}", what, error))),
+        None => Ok(result),
+    }
+}
+
+/// Poll an error scope future once
+///
+/// On the WebGL backend errors are reported synchronously, so the future
+/// returned by `pop_error_scope` is always ready.
+fn poll_now(future: impl Future<Output = Option<wgpu::Error>>) -> Option<wgpu::Error> {
+    let mut cx = Context::from_waker(Waker::noop());
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(error) => error,
+        Poll::Pending => {
+            log::warn!("Error scope did not resolve synchronously, ignoring");
+            None
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WDevice {
+    /// Push an error scope that captures errors of the given type
+    #[wasm_bindgen(js_name = pushErrorScope)]
+    pub fn push_error_scope(&self, filter: WErrorFilter) {
+        let state = self.state();
+        let state = state.borrow();
+        state.device.push_error_scope(filter.to_wgpu());
+        ERROR_SCOPE_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        log::debug!("Pushed error scope {:?}", filter);
+    }
+
+    /// Pop the most recent error scope
+    ///
+    /// Resolves to `null` if no error was captured, or to `{ type, message }`.
+    /// Rejects if there is no scope to pop.
+    #[wasm_bindgen(js_name = popErrorScope)]
+    pub fn pop_error_scope(&self) -> js_sys::Promise {
+        if ERROR_SCOPE_DEPTH.with(|depth| depth.get()) == 0 {
+            return js_sys::Promise::reject(&JsValue::from_str("popErrorScope called with no error scope pushed"));
+        }
+        ERROR_SCOPE_DEPTH.with(|depth| depth.set(depth.get() - 1));
+
+        let device = self.state().borrow().device.clone();
+        let future = device.pop_error_scope();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            Ok(match future.await {
+                Some(error) => error_to_js(&error),
+                None => JsValue::NULL,
+            })
+        })
+    }
+
+    /// Set (or clear, with `undefined`) the callback for errors not captured by any scope
+    ///
+    /// The callback receives `{ type, message }`. Without a callback, errors are logged.
+    #[wasm_bindgen(js_name = onUncapturedError)]
+    pub fn on_uncaptured_error(&self, callback: Option<js_sys::Function>) {
+        UNCAPTURED_ERROR_CALLBACK.with(|cb| {
+            *cb.borrow_mut() = callback;
+        });
+    }
+}
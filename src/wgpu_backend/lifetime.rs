@@ -0,0 +1,181 @@
+//! Resource lifetime tracking for deferred destruction
+//!
+//! Render commands are recorded and only executed at `queue.submit()`, so a buffer
+//! or texture destroyed between recording and submit would break work that is
+//! already recorded. Recorded passes hold a `ResourceUse` for every buffer and
+//! texture they reference; `destroy()` marks the resource destroyed right away
+//! (so new uses fail) but defers releasing GPU memory until the last use is gone,
+//! which happens once the command buffer has executed or been dropped.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// GPU resource whose memory is released by `destroy()`
+pub(crate) enum GpuResource {
+    Buffer(wgpu::Buffer),
+    Texture(wgpu::Texture),
+    /// Counts releases instead of touching the GPU
+    #[cfg(test)]
+    Counter(Rc<Cell<u32>>),
+}
+
+impl GpuResource {
+    fn destroy(&self) {
+        match self {
+            Self::Buffer(buffer) => buffer.destroy(),
+            Self::Texture(texture) => texture.destroy(),
+            #[cfg(test)]
+            Self::Counter(released) => released.set(released.get() + 1),
+        }
+    }
+}
+
+/// Destroy state shared by a resource wrapper, its views/bind groups and recorded commands
+pub(crate) struct ResourceState {
+    kind: &'static str,
+    destroyed: Cell<bool>,
+    /// Number of recorded, unsubmitted commands referencing the resource
+    pending_uses: Cell<u32>,
+    /// Resource to release once `pending_uses` drops to zero
+    deferred: RefCell<Option<GpuResource>>,
+}
+
+impl ResourceState {
+    pub(crate) fn new(kind: &'static str) -> Rc<Self> {
+        Rc::new(Self {
+            kind,
+            destroyed: Cell::new(false),
+            pending_uses: Cell::new(0),
+            deferred: RefCell::new(None),
+        })
+    }
+
+    pub(crate) fn is_destroyed(&self) -> bool {
+        self.destroyed.get()
+    }
+
+    /// Mark the resource destroyed, releasing it now or once recorded work no longer needs it
+    ///
+    /// Returns false if the resource was already destroyed.
+    pub(crate) fn destroy(&self, resource: GpuResource) -> bool {
+        if self.destroyed.replace(true) {
+            return false;
+        }
+        if self.pending_uses.get() == 0 {
+            resource.destroy();
+        } else {
+            log::debug!(
+                "Deferring destruction of {} until {} recorded command(s) have executed",
+                self.kind,
+                self.pending_uses.get()
+            );
+            *self.deferred.borrow_mut() = Some(resource);
+        }
+        true
+    }
+
+    /// Fail with a clear error if the resource has been destroyed
+    pub(crate) fn check_not_destroyed(&self, what: &str) -> Result<(), wasm_bindgen::JsValue> {
+        if self.destroyed.get() {
+            return Err(wasm_bindgen::JsValue::from_str(&format!("{}: {} has been destroyed", what, self.kind)));
+        }
+        Ok(())
+    }
+}
+
+/// A reference from recorded work to a resource, keeping its destruction deferred
+pub(crate) struct ResourceUse(Rc<ResourceState>);
+
+impl ResourceUse {
+    pub(crate) fn new(state: &Rc<ResourceState>) -> Self {
+        state.pending_uses.set(state.pending_uses.get() + 1);
+        Self(state.clone())
+    }
+
+    /// The resource kind ("buffer" or "texture") if it was destroyed after being recorded
+    pub(crate) fn destroyed_kind(&self) -> Option<&'static str> {
+        self.0.is_destroyed().then_some(self.0.kind)
+    }
+}
+
+impl Drop for ResourceUse {
+    fn drop(&mut self) {
+        let state = &self.0;
+        state.pending_uses.set(state.pending_uses.get() - 1);
+        if state.pending_uses.get() == 0 {
+            if let Some(resource) = state.deferred.borrow_mut().take() {
+                log::debug!("Releasing deferred {}", state.kind);
+                resource.destroy();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter() -> (Rc<Cell<u32>>, GpuResource) {
+        let released = Rc::new(Cell::new(0));
+        (released.clone(), GpuResource::Counter(released))
+    }
+
+    #[test]
+    fn destroy_without_uses_releases_immediately() {
+        let state = ResourceState::new("buffer");
+        let (released, resource) = counter();
+        assert!(state.destroy(resource));
+        assert!(state.is_destroyed());
+        assert_eq!(released.get(), 1);
+    }
+
+    #[test]
+    fn destroy_with_uses_releases_after_the_last_use() {
+        let state = ResourceState::new("texture");
+        let first = ResourceUse::new(&state);
+        let second = ResourceUse::new(&state);
+        let (released, resource) = counter();
+
+        assert!(state.destroy(resource));
+        assert!(state.is_destroyed());
+        assert_eq!(released.get(), 0);
+
+        drop(first);
+        assert_eq!(released.get(), 0);
+        drop(second);
+        assert_eq!(released.get(), 1);
+    }
+
+    #[test]
+    fn uses_after_release_do_not_release_again() {
+        let state = ResourceState::new("buffer");
+        drop(ResourceUse::new(&state));
+        let (released, resource) = counter();
+        state.destroy(resource);
+        drop(ResourceUse::new(&state));
+        assert_eq!(released.get(), 1);
+    }
+
+    #[test]
+    fn second_destroy_returns_false() {
+        let state = ResourceState::new("buffer");
+        let (released, resource) = counter();
+        assert!(state.destroy(resource));
+        let (released_again, resource) = counter();
+        assert!(!state.destroy(resource));
+        assert_eq!(released.get(), 1);
+        assert_eq!(released_again.get(), 0);
+    }
+
+    #[test]
+    fn uses_recorded_before_destroy_report_the_kind() {
+        let state = ResourceState::new("texture");
+        let recorded = ResourceUse::new(&state);
+        assert_eq!(recorded.destroyed_kind(), None);
+        assert!(state.check_not_destroyed("draw").is_ok());
+
+        let (_, resource) = counter();
+        state.destroy(resource);
+        assert_eq!(recorded.destroyed_kind(), Some("texture"));
+    }
+}
@@ -0,0 +1,431 @@
+//! Mipmap generation
+//!
+//! `generateMipmaps` fills mip levels 1.. of a texture by rendering each level
+//! from the one above it with a box filter (2x2, or three texels wide along an
+//! odd-sized axis). The GL backend can only bind a sampled view that starts at
+//! layer 0 and matches the texture's GL target (2D, 2D array or cube map), so
+//! the source view covers every layer of one level and the layer or cube face
+//! to read is passed in a small uniform.
+//!
+//! Views keep the texture's format, so sRGB textures are decoded when read and
+//! encoded when written, and the averaging happens in linear space.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+use super::adapter::{is_format_filterable, texture_format_features};
+use super::device::DeviceState;
+use super::texture::WTexture;
+
+/// Size of the per-layer uniform (the layer index, padded)
+const PARAMS_SIZE: u64 = 16;
+
+const VERTEX_SHADER: &str = r#"
+struct Params {
+    layer: u32,
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+// Fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+// textureLoad works for every float format, including non-filterable ones.
+// An odd source axis of 2n+1 texels shrinks to n, so each output texel covers
+// parts of three source texels, weighted by (n - x, n, x + 1) / (2n + 1).
+const BOX_FILTER: &str = r#"
+fn axis_weights(x: i32, size: i32, target_size: i32) -> vec3<f32> {
+    if (size & 1) == 0 {
+        return vec3<f32>(0.5, 0.5, 0.0);
+    }
+    let n = f32(target_size);
+    return vec3<f32>(n - f32(x), n, f32(x) + 1.0) / (2.0 * n + 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(source));
+    let last = size - 1;
+    let target_size = max(size / 2, vec2<i32>(1));
+    let texel = vec2<i32>(position.xy);
+    let base = texel * 2;
+    let wx = axis_weights(texel.x, size.x, target_size.x);
+    let wy = axis_weights(texel.y, size.y, target_size.y);
+
+    var color = vec4<f32>(0.0);
+    for (var j = 0; j < 3; j++) {
+        for (var i = 0; i < 3; i++) {
+            let weight = wx[i] * wy[j];
+            if weight > 0.0 {
+                color += weight * load(min(base + vec2<i32>(i, j), last));
+            }
+        }
+    }
+    return color;
+}
+"#;
+
+const FRAGMENT_2D: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+fn load(coords: vec2<i32>) -> vec4<f32> {
+    return textureLoad(source, coords, 0);
+}
+"#;
+
+const FRAGMENT_2D_ARRAY: &str = r#"
+@group(0) @binding(0) var source: texture_2d_array<f32>;
+
+fn load(coords: vec2<i32>) -> vec4<f32> {
+    return textureLoad(source, coords, i32(params.layer), 0);
+}
+"#;
+
+// Cube maps can't be loaded by texel, so sample linearly at the centre of each 2x2 block
+const FRAGMENT_CUBE: &str = r#"
+@group(0) @binding(0) var source: texture_cube<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = max(textureDimensions(source) / 2u, vec2<u32>(1u));
+    let st = position.xy / vec2<f32>(size) * 2.0 - 1.0;
+    var direction: vec3<f32>;
+    switch params.layer {
+        case 0u: { direction = vec3<f32>(1.0, -st.y, -st.x); }
+        case 1u: { direction = vec3<f32>(-1.0, -st.y, st.x); }
+        case 2u: { direction = vec3<f32>(st.x, 1.0, st.y); }
+        case 3u: { direction = vec3<f32>(st.x, -1.0, -st.y); }
+        case 4u: { direction = vec3<f32>(st.x, -st.y, 1.0); }
+        default: { direction = vec3<f32>(-st.x, -st.y, -1.0); }
+    }
+    return textureSampleLevel(source, source_sampler, direction, 0.0);
+}
+"#;
+
+/// How the levels of a texture are read while generating mipmaps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum MipmapSource {
+    D2,
+    D2Array,
+    Cube,
+}
+
+impl MipmapSource {
+    fn view_dimension(self) -> wgpu::TextureViewDimension {
+        match self {
+            Self::D2 => wgpu::TextureViewDimension::D2,
+            Self::D2Array => wgpu::TextureViewDimension::D2Array,
+            Self::Cube => wgpu::TextureViewDimension::Cube,
+        }
+    }
+
+    /// Only cube maps are sampled through a filtering sampler
+    fn filtering(self) -> bool {
+        self == Self::Cube
+    }
+
+    fn shader_source(self) -> String {
+        match self {
+            Self::D2 => format!("{}{}{}", VERTEX_SHADER, FRAGMENT_2D, BOX_FILTER),
+            Self::D2Array => format!("{}{}{}", VERTEX_SHADER, FRAGMENT_2D_ARRAY, BOX_FILTER),
+            Self::Cube => format!("{}{}", VERTEX_SHADER, FRAGMENT_CUBE),
+        }
+    }
+}
+
+/// Check that mipmaps can be generated for `texture` and pick how to read its levels
+pub(crate) fn mipmap_source(state: &DeviceState, texture: &WTexture) -> Result<MipmapSource, String> {
+    let Some(tex) = texture.inner() else {
+        return Err("Cannot generate mipmaps for the surface texture".to_string());
+    };
+    let format = tex.format();
+
+    if tex.dimension() != wgpu::TextureDimension::D2 {
+        return Err(format!("generateMipmaps supports 2D, 2D array and cube textures, not {:?}", tex.dimension()));
+    }
+    if tex.sample_count() > 1 {
+        return Err("generateMipmaps does not support multisampled textures".to_string());
+    }
+    let required = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if !tex.usage().contains(required) {
+        return Err(format!(
+            "generateMipmaps requires TEXTURE_BINDING and RENDER_ATTACHMENT usage, texture usage is {:?}",
+            tex.usage()
+        ));
+    }
+    if !matches!(format.sample_type(None, None), Some(wgpu::TextureSampleType::Float { .. })) {
+        return Err(format!("generateMipmaps needs a float color format, not {:?}", format));
+    }
+    if !texture_format_features(state, texture.format)
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    {
+        return Err(format!("{:?} is not renderable on this WebGL2 context", format));
+    }
+
+    // Same heuristic the GL backend uses to create cube maps
+    let size = tex.size();
+    let layers = size.depth_or_array_layers;
+    let cube_compatible = layers.is_multiple_of(6) && size.width == size.height;
+    let source = match (cube_compatible, layers) {
+        (false, 1) => MipmapSource::D2,
+        (false, _) => MipmapSource::D2Array,
+        (true, 6) => MipmapSource::Cube,
+        (true, _) => return Err("generateMipmaps does not support cube map arrays".to_string()),
+    };
+    if source.filtering() && !is_format_filterable(state, texture.format) {
+        return Err(format!("generateMipmaps needs a filterable format for cube maps, {:?} is not", format));
+    }
+    Ok(source)
+}
+
+/// Bindings and shader shared by every format read the same way
+struct SourcePipelineLayout {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    sampler: wgpu::Sampler,
+}
+
+impl SourcePipelineLayout {
+    fn new(device: &wgpu::Device, source: MipmapSource) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("mipmap bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: source.filtering() },
+                        view_dimension: source.view_dimension(),
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(if source.filtering() {
+                        wgpu::SamplerBindingType::Filtering
+                    } else {
+                        wgpu::SamplerBindingType::NonFiltering
+                    }),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(PARAMS_SIZE),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("mipmap pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("mipmap shader"),
+            source: wgpu::ShaderSource::Wgsl(source.shader_source().into()),
+        });
+
+        let filter = if source.filtering() {
+            wgpu::FilterMode::Linear
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mipmap sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        Self { bind_group_layout, pipeline_layout, shader, sampler }
+    }
+}
+
+/// Lazily created pipelines for `generateMipmaps`, kept per device
+#[derive(Default)]
+pub(crate) struct MipmapGenerator {
+    layouts: RefCell<HashMap<MipmapSource, SourcePipelineLayout>>,
+    pipelines: RefCell<HashMap<(wgpu::TextureFormat, MipmapSource), wgpu::RenderPipeline>>,
+    /// Per-layer uniforms and how many layers they cover (grown as needed)
+    params: RefCell<Option<(wgpu::Buffer, u32)>>,
+}
+
+impl MipmapGenerator {
+    /// Get the pipeline for a format, with the bind group layout and sampler it uses
+    fn pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        source: MipmapSource,
+    ) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout, wgpu::Sampler) {
+        let mut layouts = self.layouts.borrow_mut();
+        let layout = layouts
+            .entry(source)
+            .or_insert_with(|| SourcePipelineLayout::new(device, source));
+
+        let pipeline = self
+            .pipelines
+            .borrow_mut()
+            .entry((format, source))
+            .or_insert_with(|| {
+                log::debug!("Creating mipmap pipeline for {:?} ({:?})", format, source);
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("mipmap pipeline"),
+                    layout: Some(&layout.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &layout.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &layout.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: None,
+                })
+            })
+            .clone();
+
+        (pipeline, layout.bind_group_layout.clone(), layout.sampler.clone())
+    }
+
+    /// Get a uniform buffer holding the index of each of at least `layers` layers
+    ///
+    /// The contents only depend on the layer index, so one buffer is shared by all
+    /// textures and replaced only when a texture has more layers. Returns the
+    /// buffer and the stride between layers, usable as a dynamic offset.
+    fn params(&self, device: &wgpu::Device, layers: u32) -> (wgpu::Buffer, u32) {
+        let stride = device.limits().min_uniform_buffer_offset_alignment;
+        let mut params = self.params.borrow_mut();
+        if let Some((buffer, count)) = params.as_ref() {
+            if *count >= layers {
+                return (buffer.clone(), stride);
+            }
+        }
+
+        let mut contents = vec![0u8; stride as usize * layers as usize];
+        for layer in 0..layers {
+            let at = layer as usize * stride as usize;
+            contents[at..at + 4].copy_from_slice(&layer.to_le_bytes());
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("mipmap params"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        *params = Some((buffer.clone(), layers));
+        (buffer, stride)
+    }
+
+    /// Record the passes rendering every mip level of every layer from the level above
+    pub(crate) fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        source: MipmapSource,
+    ) {
+        let (pipeline, bind_group_layout, sampler) = self.pipeline(device, texture.format(), source);
+
+        let layers = texture.depth_or_array_layers();
+        let (params, stride) = self.params(device, layers);
+
+        for level in 1..texture.mip_level_count() {
+            let source_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mipmap source"),
+                dimension: Some(source.view_dimension()),
+                base_mip_level: level - 1,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("mipmap bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &params,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(PARAMS_SIZE),
+                        }),
+                    },
+                ],
+            });
+
+            for layer in 0..layers {
+                let target_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("mipmap target"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("mipmap pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                    multiview_mask: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[layer * stride]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+
+        log::debug!(
+            "Generated {} mip levels for {} layer(s) of {:?}",
+            texture.mip_level_count() - 1,
+            layers,
+            texture.format()
+        );
+    }
+}
@@ -14,6 +14,13 @@ mod bind_group;
 mod command;
 mod types;
 mod stats;
+mod error;
+mod adapter;
+mod lifetime;
+mod ring;
+mod queue_writes;
+mod mipmap;
+mod readback;
 
 pub use device::*;
 pub use buffer::*;
@@ -25,3 +32,5 @@ pub use bind_group::*;
 pub use command::*;
 pub use types::*;
 pub use stats::*;
+pub use error::*;
+pub use ring::*;
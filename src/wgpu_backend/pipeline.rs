@@ -11,6 +11,7 @@ use super::types::{
 };
 use super::texture::WTextureFormat;
 use super::sampler::WCompareFunction;
+use super::error::capture_error;
 use super::stats::{RENDER_PIPELINE_COUNT, RENDER_PIPELINE_DESCRIPTOR_COUNT, track_string_alloc, track_string_dealloc};
 
 /// Render pipeline
@@ -269,38 +270,39 @@ pub fn create_render_pipeline_with_pipeline_layout(
         color_format, descriptor.vertex_entry_point, descriptor.fragment_entry_point
     );
 
-    let pipeline = state
-        .device
-        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(pipeline_layout.inner()),
-            vertex: wgpu::VertexState {
-                module: shader_module.inner(),
-                entry_point: Some(&descriptor.vertex_entry_point),
-                buffers: &vertex_buffer_layouts,
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: shader_module.inner(),
-                entry_point: Some(&descriptor.fragment_entry_point),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: color_format,
-                    blend,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: descriptor.topology.to_wgpu(),
-                front_face: descriptor.front_face.to_wgpu(),
-                cull_mode: descriptor.cull_mode.to_wgpu(),
-                ..Default::default()
-            },
-            depth_stencil,
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: None,
-            cache: None,
-        });
+    let pipeline_descriptor = wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(pipeline_layout.inner()),
+        vertex: wgpu::VertexState {
+            module: shader_module.inner(),
+            entry_point: Some(&descriptor.vertex_entry_point),
+            buffers: &vertex_buffer_layouts,
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module.inner(),
+            entry_point: Some(&descriptor.fragment_entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: descriptor.topology.to_wgpu(),
+            front_face: descriptor.front_face.to_wgpu(),
+            cull_mode: descriptor.cull_mode.to_wgpu(),
+            ..Default::default()
+        },
+        depth_stencil,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    };
+    let pipeline = capture_error(&state.device, "createRenderPipeline", || {
+        state.device.create_render_pipeline(&pipeline_descriptor)
+    })?;
 
     log::debug!("Created render pipeline with explicit pipeline layout");
 
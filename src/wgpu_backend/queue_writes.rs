@@ -0,0 +1,182 @@
+//! Coalescing of queue buffer writes
+//!
+//! On the GL backend every `queue.write_buffer` becomes its own `bufferSubData`.
+//! `writeBuffer` calls are instead collected per buffer until `queue.submit()`,
+//! with adjacent and overlapping writes merged (later data wins), and then
+//! uploaded with one `write_buffer` per merged range.
+//!
+//! Pending writes are uploaded before every submit this crate makes:
+//! `queue.submit()` (which also runs recorded `clearBuffer`, `generateMipmaps` and
+//! surface capture work) and the copy made by `readTexture`. A buffer's writes
+//! are also uploaded before it is mapped or destroyed. So a write lands before
+//! any GPU work issued after it, as with an immediate `write_buffer`.
+
+use std::cell::RefCell;
+use std::sync::atomic::Ordering;
+use super::stats::{QUEUE_WRITE_BYTES_UPLOADED, QUEUE_WRITE_COUNT, QUEUE_WRITES_MERGED, QUEUE_WRITE_UPLOADS};
+
+struct PendingWrite {
+    offset: u64,
+    data: Vec<u8>,
+}
+
+impl PendingWrite {
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+// Pending writes per buffer, sorted by offset and neither overlapping nor adjacent.
+// A frame touches few distinct buffers, so a Vec searched from the most recent
+// entry is enough (wgpu::Buffer's interior mutability rules out a HashMap key).
+thread_local! {
+    static PENDING_WRITES: RefCell<Vec<(wgpu::Buffer, Vec<PendingWrite>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Queue a buffer write until the next submit, merging it with pending writes it touches
+pub(crate) fn queue_buffer_write(buffer: &wgpu::Buffer, offset: u64, data: &[u8]) {
+    QUEUE_WRITE_COUNT.fetch_add(1, Ordering::Relaxed);
+    if data.is_empty() {
+        return;
+    }
+
+    PENDING_WRITES.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let index = match pending.iter().rposition(|(b, _)| b == buffer) {
+            Some(index) => index,
+            None => {
+                pending.push((buffer.clone(), Vec::new()));
+                pending.len() - 1
+            }
+        };
+        let merged = insert_write(&mut pending[index].1, offset, data);
+        QUEUE_WRITES_MERGED.fetch_add(merged, Ordering::Relaxed);
+    });
+}
+
+/// Insert a write into a sorted list, returning how many pending writes it was merged with
+fn insert_write(writes: &mut Vec<PendingWrite>, offset: u64, data: &[u8]) -> usize {
+    let end = offset + data.len() as u64;
+
+    // Pending writes touching [offset, end), including ones that end or start exactly at its edges
+    let first = writes.partition_point(|w| w.end() < offset);
+    let last = writes.partition_point(|w| w.offset <= end);
+    if first == last {
+        writes.insert(first, PendingWrite { offset, data: data.to_vec() });
+        return 0;
+    }
+
+    let start = writes[first].offset.min(offset);
+    let merged_end = writes[last - 1].end().max(end);
+
+    // Reuse the first write's allocation when possible (the common case of appending)
+    let mut merged = if writes[first].offset == start {
+        std::mem::take(&mut writes[first].data)
+    } else {
+        Vec::new()
+    };
+    merged.resize((merged_end - start) as usize, 0);
+    for w in &writes[first..last] {
+        if !w.data.is_empty() {
+            let at = (w.offset - start) as usize;
+            merged[at..at + w.data.len()].copy_from_slice(&w.data);
+        }
+    }
+    let at = (offset - start) as usize;
+    merged[at..at + data.len()].copy_from_slice(data);
+
+    writes.splice(first..last, std::iter::once(PendingWrite { offset: start, data: merged }));
+    last - first
+}
+
+fn upload(queue: &wgpu::Queue, buffer: &wgpu::Buffer, writes: Vec<PendingWrite>) {
+    for write in writes {
+        queue.write_buffer(buffer, write.offset, &write.data);
+        QUEUE_WRITE_UPLOADS.fetch_add(1, Ordering::Relaxed);
+        QUEUE_WRITE_BYTES_UPLOADED.fetch_add(write.data.len(), Ordering::Relaxed);
+    }
+}
+
+/// Upload all pending writes (called before each submit)
+pub(crate) fn flush_buffer_writes(queue: &wgpu::Queue) {
+    let pending = PENDING_WRITES.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+    for (buffer, writes) in pending {
+        upload(queue, &buffer, writes);
+    }
+}
+
+/// Upload the pending writes of one buffer (before it is mapped or destroyed)
+pub(crate) fn flush_writes_to(queue: &wgpu::Queue, buffer: &wgpu::Buffer) {
+    let writes = PENDING_WRITES.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let index = pending.iter().position(|(b, _)| b == buffer)?;
+        Some(pending.swap_remove(index).1)
+    });
+    if let Some(writes) = writes {
+        upload(queue, buffer, writes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(writes: &[PendingWrite]) -> Vec<(u64, Vec<u8>)> {
+        writes.iter().map(|w| (w.offset, w.data.clone())).collect()
+    }
+
+    #[test]
+    fn disjoint_writes_stay_sorted() {
+        let mut writes = Vec::new();
+        assert_eq!(insert_write(&mut writes, 8, &[2, 2]), 0);
+        assert_eq!(insert_write(&mut writes, 0, &[1]), 0);
+        assert_eq!(insert_write(&mut writes, 16, &[3]), 0);
+        assert_eq!(ranges(&writes), vec![(0, vec![1]), (8, vec![2, 2]), (16, vec![3])]);
+    }
+
+    #[test]
+    fn adjacent_writes_merge() {
+        let mut writes = Vec::new();
+        insert_write(&mut writes, 4, &[1, 1]);
+        assert_eq!(insert_write(&mut writes, 6, &[2]), 1);
+        assert_eq!(insert_write(&mut writes, 2, &[3, 3]), 1);
+        assert_eq!(ranges(&writes), vec![(2, vec![3, 3, 1, 1, 2])]);
+    }
+
+    #[test]
+    fn later_data_wins_on_overlap() {
+        let mut writes = Vec::new();
+        insert_write(&mut writes, 0, &[1, 1, 1, 1]);
+        assert_eq!(insert_write(&mut writes, 2, &[2, 2, 2, 2]), 1);
+        assert_eq!(ranges(&writes), vec![(0, vec![1, 1, 2, 2, 2, 2])]);
+
+        // Fully inside an existing write
+        assert_eq!(insert_write(&mut writes, 1, &[3]), 1);
+        assert_eq!(ranges(&writes), vec![(0, vec![1, 3, 2, 2, 2, 2])]);
+    }
+
+    #[test]
+    fn write_spanning_several_pending_writes_merges_them_all() {
+        let mut writes = Vec::new();
+        insert_write(&mut writes, 0, &[1, 1]);
+        insert_write(&mut writes, 4, &[2, 2]);
+        insert_write(&mut writes, 8, &[3, 3]);
+        insert_write(&mut writes, 20, &[4]);
+
+        assert_eq!(insert_write(&mut writes, 1, &[5, 5, 5, 5, 5, 5, 5, 5]), 3);
+        assert_eq!(
+            ranges(&writes),
+            vec![(0, vec![1, 5, 5, 5, 5, 5, 5, 5, 5, 3]), (20, vec![4])]
+        );
+    }
+
+    #[test]
+    fn gaps_between_merged_writes_are_covered_by_the_new_write() {
+        let mut writes = Vec::new();
+        insert_write(&mut writes, 4, &[1]);
+        insert_write(&mut writes, 8, &[2]);
+        // Starts before the first pending write, so its allocation can't be reused
+        assert_eq!(insert_write(&mut writes, 2, &[3, 3, 3, 3, 3, 3, 3]), 2);
+        assert_eq!(ranges(&writes), vec![(2, vec![3, 3, 3, 3, 3, 3, 3])]);
+    }
+}
@@ -0,0 +1,647 @@
+//! Texture and surface readback
+//!
+//! `readTexture` copies a texture region into a staging buffer and maps it.
+//! Buffer rows are padded to COPY_BYTES_PER_ROW_ALIGNMENT (256 bytes); the
+//! padding is stripped before the bytes are handed to JS.
+//!
+//! The GL backend's surface texture can only be rendered to, not copied from.
+//! `captureSurface` therefore has the surface passes of the next frame render
+//! into an offscreen texture, which is copied to a staging buffer and then drawn
+//! onto the surface with a fullscreen pass before presenting.
+
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use super::device::{DeviceState, WQueue};
+use super::error::capture_error;
+use super::buffer::{wait_for_map, MapResult};
+use super::queue_writes::flush_buffer_writes;
+use super::texture::{WTexture, WTextureAspect};
+
+const BLIT_SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+// Fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
+"#;
+
+/// A pending `captureSurface` call
+struct CaptureRequest {
+    resolve: js_sys::Function,
+    reject: js_sys::Function,
+    png: bool,
+}
+
+// Requests waiting for the next frame that renders to the surface
+thread_local! {
+    static CAPTURE_REQUESTS: RefCell<Vec<CaptureRequest>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Map a staging buffer and copy out its contents
+async fn read_buffer(device: wgpu::Device, buffer: wgpu::Buffer) -> Result<Vec<u8>, JsValue> {
+    // The callback must be Send, so it only records the result
+    let result: MapResult = Arc::new(Mutex::new(None));
+    let callback_result = result.clone();
+    buffer.slice(..).map_async(wgpu::MapMode::Read, move |r| {
+        *callback_result.lock().unwrap() = Some(r);
+    });
+
+    wait_for_map(device, result)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Readback failed: {}", e)))?;
+    let data = buffer.slice(..).get_mapped_range().to_vec();
+    buffer.unmap();
+    Ok(data)
+}
+
+/// Drop the padding at the end of each `padded_row`-byte row
+fn unpad_rows(data: &[u8], padded_row: usize, row: usize) -> Vec<u8> {
+    if padded_row == row {
+        return data.to_vec();
+    }
+    data.chunks(padded_row).flat_map(|chunk| &chunk[..row]).copied().collect()
+}
+
+/// Buffer layout for copying a `width` x `height` region of `aspect` of `format`
+///
+/// Returns (bytes per row, padded bytes per row, rows per image).
+fn copy_layout(
+    format: wgpu::TextureFormat,
+    aspect: wgpu::TextureAspect,
+    width: u32,
+    height: u32,
+) -> Option<(u32, u32, u32)> {
+    let block_size = format.block_copy_size(Some(aspect))?;
+    let (block_width, block_height) = format.block_dimensions();
+    let row = width.div_ceil(block_width) * block_size;
+    Some((row, row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT), height.div_ceil(block_height)))
+}
+
+/// Fullscreen copy pipelines used to draw a captured frame onto the surface
+#[derive(Default)]
+pub(crate) struct SurfaceBlit {
+    pipelines: RefCell<HashMap<wgpu::TextureFormat, (wgpu::RenderPipeline, wgpu::BindGroupLayout)>>,
+}
+
+impl SurfaceBlit {
+    fn pipeline(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout) {
+        self.pipelines
+            .borrow_mut()
+            .entry(format)
+            .or_insert_with(|| {
+                log::debug!("Creating surface blit pipeline for {:?}", format);
+                let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("surface blit bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    }],
+                });
+                let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("surface blit pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("surface blit shader"),
+                    source: wgpu::ShaderSource::Wgsl(BLIT_SHADER.into()),
+                });
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("surface blit pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: None,
+                });
+                (pipeline, bind_group_layout)
+            })
+            .clone()
+    }
+}
+
+/// Offscreen stand-in for the surface while a frame is being captured
+pub(crate) struct SurfaceCapture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    requests: Vec<CaptureRequest>,
+}
+
+/// A captured frame whose staging buffer is read after the frame is submitted
+pub(crate) struct PendingCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    row: u32,
+    padded_row: u32,
+    bgra: bool,
+    requests: Vec<CaptureRequest>,
+}
+
+/// Start capturing this frame if `captureSurface` was called, returning the view to render to instead of the surface
+pub(crate) fn begin_surface_capture(state: &DeviceState) -> Option<SurfaceCapture> {
+    let requests = CAPTURE_REQUESTS.with(|requests| std::mem::take(&mut *requests.borrow_mut()));
+    if requests.is_empty() {
+        return None;
+    }
+
+    let format = state.surface_config.format;
+    if format.remove_srgb_suffix() != wgpu::TextureFormat::Rgba8Unorm
+        && format.remove_srgb_suffix() != wgpu::TextureFormat::Bgra8Unorm
+    {
+        let error = JsValue::from_str(&format!("captureSurface only supports 8-bit RGBA/BGRA surfaces, not {:?}", format));
+        for request in requests {
+            let _ = request.reject.call1(&JsValue::NULL, &error);
+        }
+        return None;
+    }
+
+    let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("surface capture"),
+        size: wgpu::Extent3d {
+            width: state.surface_config.width,
+            height: state.surface_config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    log::debug!("Capturing surface frame for {} request(s)", requests.len());
+    Some(SurfaceCapture { texture, view, requests })
+}
+
+impl SurfaceCapture {
+    /// View the frame's surface passes render to
+    pub(crate) fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Record the copy of the captured frame to a staging buffer and its blit onto the surface
+    pub(crate) fn finish(
+        self,
+        state: &DeviceState,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+    ) -> PendingCapture {
+        let format = self.texture.format();
+        let size = self.texture.size();
+        let (row, padded_row, _) = copy_layout(format, wgpu::TextureAspect::All, size.width, size.height)
+            .expect("capture texture has an 8-bit color format");
+
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("surface capture readback"),
+            size: padded_row as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+
+        let (pipeline, bind_group_layout) = state.surface_blit.pipeline(&state.device, format);
+        let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("surface blit bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&self.view),
+            }],
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("surface blit pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        PendingCapture {
+            buffer,
+            width: size.width,
+            height: size.height,
+            row,
+            padded_row,
+            bgra: format.remove_srgb_suffix() == wgpu::TextureFormat::Bgra8Unorm,
+            requests: self.requests,
+        }
+    }
+}
+
+impl PendingCapture {
+    /// Settle the `captureSurface` promises once the staging buffer is mapped
+    ///
+    /// Call after the frame has been submitted; mapping completes on a later task.
+    pub(crate) fn read_when_mapped(self, device: &wgpu::Device) {
+        let device = device.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let data = read_buffer(device, self.buffer).await.map(|data| {
+                let mut rgba = unpad_rows(&data, self.padded_row as usize, self.row as usize);
+                if self.bgra {
+                    for pixel in rgba.chunks_exact_mut(4) {
+                        pixel.swap(0, 2);
+                    }
+                }
+                rgba
+            });
+
+            let mut png = None;
+            for request in self.requests {
+                let rgba = match &data {
+                    Ok(rgba) => rgba,
+                    Err(e) => {
+                        let _ = request.reject.call1(&JsValue::NULL, e);
+                        continue;
+                    }
+                };
+                let bytes = if request.png {
+                    png.get_or_insert_with(|| encode_png(self.width, self.height, rgba)).as_slice()
+                } else {
+                    rgba.as_slice()
+                };
+
+                let result = js_sys::Object::new();
+                let _ = js_sys::Reflect::set(&result, &"width".into(), &self.width.into());
+                let _ = js_sys::Reflect::set(&result, &"height".into(), &self.height.into());
+                let _ = js_sys::Reflect::set(&result, &"data".into(), &js_sys::Uint8Array::from(bytes).into());
+                let _ = request.resolve.call1(&JsValue::NULL, &result);
+            }
+            log::debug!("Captured {}x{} surface frame", self.width, self.height);
+        });
+    }
+}
+
+/// CRC-32 (ISO-HDLC) as used by PNG chunks
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for chunk in chunks {
+        for &byte in *chunk {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+    }
+    !crc
+}
+
+/// Encode RGBA8 pixels as a PNG
+///
+/// Screenshots are for tests and bug reports, so the image data is stored
+/// uncompressed (deflate "stored" blocks) rather than pulling in a compressor.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        png.extend_from_slice(&crc32(&[kind, data]).to_be_bytes());
+    }
+
+    // Each scanline starts with filter type 0 (none)
+    let row = width as usize * 4;
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in rgba.chunks_exact(row) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    // zlib stream of stored blocks, followed by the Adler-32 of the raw data
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = raw.chunks(0xFFFF);
+    let count = blocks.len();
+    for (i, block) in blocks.enumerate() {
+        zlib.push((i + 1 == count) as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    if count == 0 {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in &raw {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, no filter, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[wasm_bindgen]
+impl WQueue {
+    /// Read a region of a texture back as tightly packed bytes
+    ///
+    /// Resolves to a Uint8Array with rows of `width` texels (or blocks) and no
+    /// padding. `width`, `height` and `depth` default to the rest of the mip
+    /// level. `aspect` (default All) selects the depth or stencil of a
+    /// depth-stencil texture. The texture needs COPY_SRC usage. Texture writes and
+    /// work submitted before the call are included; commands recorded but not yet
+    /// submitted are not. Use `captureSurface` to read the canvas.
+    #[wasm_bindgen(js_name = readTexture)]
+    pub fn read_texture(
+        &self,
+        texture: &WTexture,
+        mip_level: u32,
+        origin_x: u32,
+        origin_y: u32,
+        origin_z: u32,
+        width: Option<u32>,
+        height: Option<u32>,
+        depth: Option<u32>,
+        aspect: Option<WTextureAspect>,
+    ) -> js_sys::Promise {
+        let aspect = aspect.unwrap_or(WTextureAspect::All);
+        let readback = self.copy_texture_to_staging(texture, mip_level, origin_x, origin_y, origin_z, width, height, depth, aspect);
+        let (device, buffer, row, padded_row) = match readback {
+            Ok(readback) => readback,
+            Err(e) => return js_sys::Promise::reject(&e),
+        };
+        wasm_bindgen_futures::future_to_promise(async move {
+            let data = read_buffer(device, buffer).await?;
+            let bytes = unpad_rows(&data, padded_row as usize, row as usize);
+            Ok(js_sys::Uint8Array::from(bytes.as_slice()).into())
+        })
+    }
+
+    /// Read the next frame presented to the canvas
+    ///
+    /// Resolves to `{ width, height, data }` once the next `queue.submit()` that
+    /// renders to the surface has run, with `data` as RGBA8 bytes or, if `png`
+    /// is true, a PNG file. Only 8-bit RGBA/BGRA surfaces can be captured.
+    #[wasm_bindgen(js_name = captureSurface)]
+    pub fn capture_surface(&self, png: Option<bool>) -> js_sys::Promise {
+        js_sys::Promise::new(&mut |resolve, reject| {
+            CAPTURE_REQUESTS.with(|requests| {
+                requests.borrow_mut().push(CaptureRequest {
+                    resolve,
+                    reject,
+                    png: png.unwrap_or(false),
+                });
+            });
+        })
+    }
+}
+
+impl WQueue {
+    /// Submit a copy of the region to a new staging buffer
+    ///
+    /// Returns the device and buffer to map, with the unpadded and padded row sizes.
+    fn copy_texture_to_staging(
+        &self,
+        texture: &WTexture,
+        mip_level: u32,
+        origin_x: u32,
+        origin_y: u32,
+        origin_z: u32,
+        width: Option<u32>,
+        height: Option<u32>,
+        depth: Option<u32>,
+        aspect: WTextureAspect,
+    ) -> Result<(wgpu::Device, wgpu::Buffer, u32, u32), JsValue> {
+        texture.check_not_destroyed("readTexture")?;
+        aspect.check_format(texture.format, "readTexture")?;
+        let Some(tex) = texture.inner() else {
+            return Err(JsValue::from_str("readTexture cannot read the surface texture, use captureSurface"));
+        };
+        if !tex.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(JsValue::from_str(&format!("readTexture requires COPY_SRC usage, texture usage is {:?}", tex.usage())));
+        }
+        if mip_level >= tex.mip_level_count() {
+            return Err(JsValue::from_str(&format!(
+                "readTexture mip level {} out of range (texture has {})",
+                mip_level,
+                tex.mip_level_count()
+            )));
+        }
+
+        let mip_size = tex.size().mip_level_size(mip_level, tex.dimension());
+        let extent = wgpu::Extent3d {
+            width: width.unwrap_or(mip_size.width.saturating_sub(origin_x)),
+            height: height.unwrap_or(mip_size.height.saturating_sub(origin_y)),
+            depth_or_array_layers: depth.unwrap_or(mip_size.depth_or_array_layers.saturating_sub(origin_z)),
+        };
+        if extent.width == 0 || extent.height == 0 || extent.depth_or_array_layers == 0 {
+            return Err(JsValue::from_str("readTexture region is empty"));
+        }
+        let Some((row, padded_row, rows)) = copy_layout(tex.format(), aspect.to_wgpu(), extent.width, extent.height) else {
+            return Err(JsValue::from_str(&format!(
+                "readTexture cannot read all of {:?}, read the depth or stencil aspect separately",
+                texture.format
+            )));
+        };
+
+        let state = self.state();
+        let state = state.borrow();
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readTexture readback"),
+            size: padded_row as u64 * rows as u64 * extent.depth_or_array_layers as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("readTexture encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: tex,
+                mip_level,
+                origin: wgpu::Origin3d {
+                    x: origin_x,
+                    y: origin_y,
+                    z: origin_z,
+                },
+                aspect: aspect.to_wgpu(),
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(rows),
+                },
+            },
+            extent,
+        );
+        // Keep pending writeBuffer calls ahead of this submit
+        flush_buffer_writes(&state.queue);
+        capture_error(&state.device, "readTexture", || {
+            state.queue.submit(std::iter::once(encoder.finish()));
+        })?;
+
+        log::debug!(
+            "Reading back {}x{}x{} of mip {} ({} bytes per row, padded to {})",
+            extent.width, extent.height, extent.depth_or_array_layers, mip_level, row, padded_row
+        );
+        Ok((state.device.clone(), buffer, row, padded_row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split a PNG into (chunk type, data), checking each chunk's length and CRC
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut at = 8;
+        while at < png.len() {
+            let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = png[at + 4..at + 8].try_into().unwrap();
+            let data = &png[at + 8..at + 8 + len];
+            let crc = u32::from_be_bytes(png[at + 8 + len..at + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(&[&kind, data]), "CRC of {:?}", std::str::from_utf8(&kind));
+            chunks.push((kind, data.to_vec()));
+            at += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn crc32_matches_reference_values() {
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"IEND"]), 0xAE42_6082);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn unpad_rows_drops_row_padding() {
+        let data = [1, 2, 3, 0, 0, 4, 5, 6, 0, 0];
+        assert_eq!(unpad_rows(&data, 5, 3), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(unpad_rows(&data, 5, 5), data.to_vec());
+    }
+
+    #[test]
+    fn png_of_one_pixel() {
+        let png = encode_png(1, 1, &[255, 0, 0, 255]);
+        let chunks = chunks(&png);
+        assert_eq!(chunks.len(), 3);
+
+        assert_eq!(&chunks[0].0, b"IHDR");
+        assert_eq!(chunks[0].1, [0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+
+        // zlib header, one final stored block of the filtered scanline, Adler-32
+        assert_eq!(&chunks[1].0, b"IDAT");
+        assert_eq!(
+            chunks[1].1,
+            [0x78, 0x01, 0x01, 0x05, 0x00, 0xFA, 0xFF, 0, 255, 0, 0, 255, 0x05, 0x00, 0x01, 0xFF]
+        );
+
+        assert_eq!(&chunks[2].0, b"IEND");
+        assert!(chunks[2].1.is_empty());
+        assert_eq!(&png[png.len() - 4..], [0xAE, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn png_larger_than_one_stored_block() {
+        // 200x100 RGBA is 100 * (1 + 800) = 80100 bytes of scanlines
+        let (width, height) = (200, 100);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i % 251) as u8).collect();
+        let png = encode_png(width, height, &rgba);
+        let idat = &chunks(&png)[1].1;
+
+        let mut raw = Vec::new();
+        let mut at = 2;
+        loop {
+            let last = idat[at] == 1;
+            let len = u16::from_le_bytes([idat[at + 1], idat[at + 2]]);
+            let nlen = u16::from_le_bytes([idat[at + 3], idat[at + 4]]);
+            assert_eq!(len, !nlen);
+            raw.extend_from_slice(&idat[at + 5..at + 5 + len as usize]);
+            at += 5 + len as usize;
+            if last {
+                break;
+            }
+            assert_eq!(len, 0xFFFF);
+        }
+        assert_eq!(raw.len(), 80100);
+        assert_eq!(at + 4, idat.len());
+
+        for (y, line) in raw.chunks(801).enumerate() {
+            assert_eq!(line[0], 0);
+            assert_eq!(&line[1..], &rgba[y * 800..(y + 1) * 800]);
+        }
+
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in &raw {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(idat[at..], ((b << 16) | a).to_be_bytes());
+    }
+}
@@ -0,0 +1,296 @@
+//! Per-frame uniform ring allocator
+//!
+//! Hands out aligned slices of one GPU buffer for small per-object data. Data is
+//! staged in a CPU copy of the buffer and uploaded with a single `write_buffer`
+//! per submit (two when the ring wraps), instead of one `bufferSubData` per object.
+//! The returned offsets are meant to be used as dynamic bind group offsets.
+//!
+//! Space is recycled per frame, where each `queue.submit()` ends a frame: the
+//! ring keeps the data of the current frame plus `frames_in_flight` previous ones.
+
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+use super::buffer::{buffer_usage, WBuffer};
+use super::device::WDevice;
+
+// Rings register themselves so `queue.submit()` can flush them
+thread_local! {
+    static RINGS: RefCell<Vec<Weak<RefCell<RingState>>>> = const { RefCell::new(Vec::new()) };
+}
+
+struct RingState {
+    buffer: WBuffer,
+    /// CPU copy of the buffer contents
+    staging: Vec<u8>,
+    cursor: RingCursor,
+}
+
+/// Space bookkeeping of a ring, independent of the buffer
+struct RingCursor {
+    capacity: u64,
+    alignment: u64,
+    frames_in_flight: usize,
+    // Positions are virtual (monotonically increasing); the buffer offset is `position % capacity`
+    head: u64,
+    /// Start of the data not yet uploaded, if any
+    dirty_start: Option<u64>,
+    /// Start position of each live frame, oldest first (the last one is the current frame)
+    frame_starts: VecDeque<u64>,
+}
+
+impl RingCursor {
+    fn new(capacity: u64, alignment: u64, frames_in_flight: usize) -> Self {
+        Self {
+            capacity,
+            alignment,
+            frames_in_flight,
+            head: 0,
+            dirty_start: None,
+            frame_starts: VecDeque::from([0]),
+        }
+    }
+
+    /// Reserve `size` bytes at an aligned offset, returning the virtual position
+    fn allocate(&mut self, size: u64) -> Result<u64, String> {
+        let capacity = self.capacity;
+        let offset = self.head % capacity;
+        let aligned = offset.next_multiple_of(self.alignment);
+
+        // Slices never straddle the end of the buffer; skip to the start instead
+        let start = if aligned + size <= capacity {
+            self.head - offset + aligned
+        } else {
+            self.head - offset + capacity
+        };
+        let end = start + size;
+
+        let oldest = self.frame_starts.front().copied().unwrap_or(0);
+        if size > capacity || end - oldest > capacity {
+            return Err(format!(
+                "Uniform ring is full: cannot allocate {} bytes ({} of {} bytes in use by the last {} frame(s))",
+                size,
+                self.head - oldest,
+                capacity,
+                self.frame_starts.len()
+            ));
+        }
+
+        self.dirty_start.get_or_insert(start);
+        self.head = end;
+        Ok(start)
+    }
+
+    /// Take the buffer ranges written since the last call
+    ///
+    /// The second range is only non-empty when the data wraps around the end of
+    /// the buffer; both are empty if nothing was written.
+    fn take_dirty(&mut self) -> [Range<u64>; 2] {
+        let Some(dirty_start) = self.dirty_start.take() else {
+            return [0..0, 0..0];
+        };
+        let start = dirty_start % self.capacity;
+        let end = start + (self.head - dirty_start);
+        if end <= self.capacity {
+            [start..end, 0..0]
+        } else {
+            [start..self.capacity, 0..end - self.capacity]
+        }
+    }
+
+    /// Start a new frame, releasing the space of frames older than `frames_in_flight`
+    fn end_frame(&mut self) {
+        self.frame_starts.push_back(self.head);
+        while self.frame_starts.len() > self.frames_in_flight + 1 {
+            self.frame_starts.pop_front();
+        }
+    }
+}
+
+impl RingState {
+    fn capacity(&self) -> u64 {
+        self.cursor.capacity
+    }
+
+    /// Upload the staged data written since the last flush
+    fn flush(&mut self, queue: &wgpu::Queue) {
+        let dirty = self.cursor.take_dirty();
+        if self.buffer.lifetime.is_destroyed() {
+            return;
+        }
+
+        // Round the end up to COPY_BUFFER_ALIGNMENT; capacity is a multiple of it
+        let capacity = self.capacity();
+        for range in dirty.into_iter().filter(|range| !range.is_empty()) {
+            let end = range.end.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT).min(capacity);
+            queue.write_buffer(self.buffer.inner(), range.start, &self.staging[range.start as usize..end as usize]);
+        }
+    }
+}
+
+/// Upload every ring's staged data and start a new frame (called by `queue.submit()`)
+pub(crate) fn flush_uniform_rings(queue: &wgpu::Queue) {
+    RINGS.with(|rings| {
+        let mut rings = rings.borrow_mut();
+        rings.retain(|ring| ring.strong_count() > 0);
+        for ring in rings.iter().filter_map(Weak::upgrade) {
+            let mut ring = ring.borrow_mut();
+            ring.flush(queue);
+            ring.cursor.end_frame();
+        }
+    });
+}
+
+/// Ring buffer suballocator for per-frame uniform data
+#[wasm_bindgen]
+pub struct WUniformRing {
+    state: Rc<RefCell<RingState>>,
+}
+
+#[wasm_bindgen]
+impl WUniformRing {
+    /// Create a ring of `capacity` bytes
+    ///
+    /// `usage` is combined with COPY_DST (e.g. pass UNIFORM or STORAGE).
+    /// `frames_in_flight` (default 2) is how many submitted frames keep their data
+    /// before the space is reused. The capacity is rounded up to the device's
+    /// minUniformBufferOffsetAlignment.
+    #[wasm_bindgen(constructor)]
+    pub fn new(device: &WDevice, capacity: u32, usage: u32, frames_in_flight: Option<u32>) -> WUniformRing {
+        let state = device.state();
+        let state = state.borrow();
+
+        let alignment = state.device.limits().min_uniform_buffer_offset_alignment as u64;
+        let capacity = (capacity as u64).max(alignment).next_multiple_of(alignment);
+        let usage = usage | buffer_usage::COPY_DST;
+
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform ring"),
+            size: capacity,
+            usage: wgpu::BufferUsages::from_bits_truncate(usage),
+            mapped_at_creation: false,
+        });
+
+        log::debug!("Created uniform ring: capacity={}, alignment={}", capacity, alignment);
+
+        let ring = Rc::new(RefCell::new(RingState {
+            buffer: WBuffer::new(buffer, capacity, usage),
+            staging: vec![0; capacity as usize],
+            cursor: RingCursor::new(capacity, alignment, frames_in_flight.unwrap_or(2) as usize),
+        }));
+        RINGS.with(|rings| rings.borrow_mut().push(Rc::downgrade(&ring)));
+
+        WUniformRing { state: ring }
+    }
+
+    /// The ring's buffer, to bind with `addBuffer` and a dynamic offset
+    ///
+    /// Destroying the returned buffer destroys the ring's buffer.
+    #[wasm_bindgen(getter)]
+    pub fn buffer(&self) -> WBuffer {
+        self.state.borrow().buffer.share()
+    }
+
+    /// Size of the ring in bytes
+    #[wasm_bindgen(getter)]
+    pub fn capacity(&self) -> u32 {
+        self.state.borrow().capacity() as u32
+    }
+
+    /// Alignment of the offsets returned by `push` (minUniformBufferOffsetAlignment)
+    #[wasm_bindgen(getter)]
+    pub fn alignment(&self) -> u32 {
+        self.state.borrow().cursor.alignment as u32
+    }
+
+    /// Copy `data` into the ring and return its offset in the buffer
+    ///
+    /// The data is uploaded at the next `queue.submit()`. Fails if the ring has no
+    /// room left for the frames still in flight.
+    pub fn push(&self, data: &[u8]) -> Result<u32, JsValue> {
+        let mut ring = self.state.borrow_mut();
+        ring.buffer.check_not_destroyed("WUniformRing.push")?;
+
+        let start = ring.cursor.allocate(data.len() as u64).map_err(|e| JsValue::from_str(&e))?;
+        let offset = (start % ring.capacity()) as usize;
+        ring.staging[offset..offset + data.len()].copy_from_slice(data);
+        Ok(offset as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_are_aligned() {
+        let mut cursor = RingCursor::new(1024, 256, 2);
+        assert_eq!(cursor.allocate(16), Ok(0));
+        assert_eq!(cursor.allocate(16), Ok(256));
+        assert_eq!(cursor.allocate(300), Ok(512));
+        assert_eq!(cursor.take_dirty(), [0..812, 0..0]);
+        assert_eq!(cursor.take_dirty(), [0..0, 0..0]);
+    }
+
+    #[test]
+    fn allocation_that_would_straddle_the_end_wraps_to_the_start() {
+        let mut cursor = RingCursor::new(1024, 256, 1);
+        assert_eq!(cursor.allocate(700), Ok(0));
+        cursor.take_dirty();
+        cursor.end_frame();
+        cursor.end_frame();
+
+        // 768 + 300 > 1024, so the slice starts at the next lap (offset 0)
+        assert_eq!(cursor.allocate(300), Ok(1024));
+        assert_eq!(cursor.take_dirty(), [0..300, 0..0]);
+    }
+
+    #[test]
+    fn allocation_exactly_filling_the_end_does_not_wrap() {
+        let mut cursor = RingCursor::new(1024, 256, 0);
+        assert_eq!(cursor.allocate(768), Ok(0));
+        cursor.end_frame();
+        assert_eq!(cursor.allocate(256), Ok(768));
+        assert_eq!(cursor.take_dirty(), [0..1024, 0..0]);
+        cursor.end_frame();
+        assert_eq!(cursor.allocate(256), Ok(1024));
+    }
+
+    #[test]
+    fn dirty_data_wrapping_around_is_split_in_two_ranges() {
+        let mut cursor = RingCursor::new(1024, 256, 0);
+        assert_eq!(cursor.allocate(512), Ok(0));
+        cursor.take_dirty();
+        cursor.end_frame();
+
+        assert_eq!(cursor.allocate(256), Ok(512));
+        assert_eq!(cursor.allocate(256), Ok(768));
+        assert_eq!(cursor.allocate(100), Ok(1024));
+        assert_eq!(cursor.take_dirty(), [512..1024, 0..100]);
+    }
+
+    #[test]
+    fn space_of_frames_in_flight_is_not_reused() {
+        let mut cursor = RingCursor::new(1024, 256, 1);
+        assert_eq!(cursor.allocate(512), Ok(0));
+        cursor.end_frame();
+        assert_eq!(cursor.allocate(512), Ok(512));
+        cursor.end_frame();
+
+        // Frame 0 is released once two newer frames have started
+        assert_eq!(cursor.allocate(512), Ok(1024));
+        assert!(cursor.allocate(256).is_err());
+    }
+
+    #[test]
+    fn full_ring_and_oversized_allocations_fail() {
+        let mut cursor = RingCursor::new(1024, 256, 2);
+        assert!(cursor.allocate(2048).is_err());
+        assert_eq!(cursor.allocate(1024), Ok(0));
+        assert!(cursor.allocate(1).is_err());
+        // A failed allocation leaves the ring unchanged
+        assert_eq!(cursor.head, 1024);
+    }
+}
@@ -4,6 +4,7 @@ use wasm_bindgen::prelude::*;
 use std::sync::atomic::Ordering;
 use super::device::WDevice;
 use super::stats::SAMPLER_COUNT;
+use super::error::capture_error;
 
 /// Address mode for texture sampling
 #[wasm_bindgen]
@@ -158,7 +159,7 @@ pub fn create_sampler(
     // max_anisotropy must be >= 1, clamp to valid range
     let anisotropy = max_anisotropy.max(1).min(16);
 
-    let sampler = state.device.create_sampler(&wgpu::SamplerDescriptor {
+    let descriptor = wgpu::SamplerDescriptor {
         label: None,
         address_mode_u: address_mode_u.to_wgpu(),
         address_mode_v: address_mode_v.to_wgpu(),
@@ -171,7 +172,8 @@ pub fn create_sampler(
         compare: compare.to_wgpu(),
         anisotropy_clamp: anisotropy,
         ..Default::default()
-    });
+    };
+    let sampler = capture_error(&state.device, "createSampler", || state.device.create_sampler(&descriptor))?;
 
     log::debug!("Created sampler with lod=[{}, {}], compare={:?}, anisotropy={}",
         lod_min_clamp, lod_max_clamp, compare, anisotropy);
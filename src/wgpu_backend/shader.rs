@@ -4,6 +4,7 @@ use wasm_bindgen::prelude::*;
 use std::sync::atomic::Ordering;
 use super::device::WDevice;
 use super::stats::SHADER_MODULE_COUNT;
+use super::error::capture_error;
 
 /// WebGPU Shader Module wrapper
 #[wasm_bindgen]
@@ -37,10 +38,12 @@ pub fn create_shader_module(
     let state = device.state();
     let state = state.borrow();
 
-    let module = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: wgpu::ShaderSource::Wgsl(wgsl_source.into()),
-    });
+    let module = capture_error(&state.device, "createShaderModule", || {
+        state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(wgsl_source.into()),
+        })
+    })?;
 
     log::debug!("Created shader module");
 
@@ -94,3 +94,35 @@ pub fn track_string_alloc(s: &str) {
 pub fn track_string_dealloc(len: usize) {
     STRING_BYTES_ALLOCATED.fetch_sub(len, Ordering::Relaxed);
 }
+
+// Queue write coalescing counters (see queue_writes.rs)
+pub static QUEUE_WRITE_COUNT: AtomicUsize = AtomicUsize::new(0);
+pub static QUEUE_WRITES_MERGED: AtomicUsize = AtomicUsize::new(0);
+pub static QUEUE_WRITE_UPLOADS: AtomicUsize = AtomicUsize::new(0);
+pub static QUEUE_WRITE_BYTES_UPLOADED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns buffer write coalescing statistics
+///
+/// `writes` is the number of writeBuffer calls, `merged` how many of them were
+/// merged into another pending write, and `uploads`/`bytesUploaded` what was
+/// actually sent to the GPU at submit.
+#[wasm_bindgen(js_name = getQueueWriteStats)]
+pub fn get_queue_write_stats() -> JsValue {
+    let stats = js_sys::Object::new();
+
+    let _ = js_sys::Reflect::set(&stats, &"writes".into(), &(QUEUE_WRITE_COUNT.load(Ordering::Relaxed) as u32).into());
+    let _ = js_sys::Reflect::set(&stats, &"merged".into(), &(QUEUE_WRITES_MERGED.load(Ordering::Relaxed) as u32).into());
+    let _ = js_sys::Reflect::set(&stats, &"uploads".into(), &(QUEUE_WRITE_UPLOADS.load(Ordering::Relaxed) as u32).into());
+    let _ = js_sys::Reflect::set(&stats, &"bytesUploaded".into(), &(QUEUE_WRITE_BYTES_UPLOADED.load(Ordering::Relaxed) as f64).into());
+
+    stats.into()
+}
+
+/// Resets the buffer write coalescing counters to zero
+#[wasm_bindgen(js_name = resetQueueWriteStats)]
+pub fn reset_queue_write_stats() {
+    QUEUE_WRITE_COUNT.store(0, Ordering::Relaxed);
+    QUEUE_WRITES_MERGED.store(0, Ordering::Relaxed);
+    QUEUE_WRITE_UPLOADS.store(0, Ordering::Relaxed);
+    QUEUE_WRITE_BYTES_UPLOADED.store(0, Ordering::Relaxed);
+}
@@ -1,9 +1,14 @@
 //! Texture and TextureView wrappers
 
 use wasm_bindgen::prelude::*;
+use std::rc::Rc;
 use std::sync::atomic::Ordering;
-use super::device::{WDevice, WQueue};
+use super::device::{DeviceState, WDevice, WQueue};
 use super::stats::{TEXTURE_COUNT, TEXTURE_VIEW_COUNT};
+use super::error::capture_error;
+use super::lifetime::{GpuResource, ResourceState};
+use super::adapter::flag_name_to_js;
+use super::types::upload_conversion;
 
 /// Texture format enum (matching WebGPU, values match .d.ts)
 #[wasm_bindgen]
@@ -16,12 +21,28 @@ pub enum WTextureFormat {
     R8Uint = 2,
     R8Sint = 3,
 
+    // R 16/32-bit formats (values match .d.ts: 4-9)
+    R16Uint = 4,
+    R16Sint = 5,
+    R16Float = 6,
+    R32Uint = 7,
+    R32Sint = 8,
+    R32Float = 9,
+
     // RG 8-bit formats (values match .d.ts: 10-13)
     Rg8Unorm = 10,
     Rg8Snorm = 11,
     Rg8Uint = 12,
     Rg8Sint = 13,
 
+    // RG 16/32-bit formats (values match .d.ts: 14-19)
+    Rg16Uint = 14,
+    Rg16Sint = 15,
+    Rg16Float = 16,
+    Rg32Uint = 17,
+    Rg32Sint = 18,
+    Rg32Float = 19,
+
     // RGBA 8-bit formats (values match .d.ts: 20-26)
     Rgba8Unorm = 20,
     Rgba8UnormSrgb = 21,
@@ -31,24 +52,113 @@ pub enum WTextureFormat {
     Bgra8Unorm = 25,
     Bgra8UnormSrgb = 26,
 
-    // Depth formats (values match .d.ts: 50-53)
+    // Packed 32-bit formats (values match .d.ts: 27-29)
+    Rgb10a2Unorm = 27,
+    Rgb10a2Uint = 28,
+    Rg11b10Ufloat = 29,
+
+    // RGBA 16/32-bit formats (values match .d.ts: 30-35)
+    Rgba16Uint = 30,
+    Rgba16Sint = 31,
+    Rgba16Float = 32,
+    Rgba32Uint = 33,
+    Rgba32Sint = 34,
+    Rgba32Float = 35,
+
+    // Shared-exponent format, sample-only (value matches .d.ts: 36)
+    Rgb9e5Ufloat = 36,
+
+    // Depth formats (values match .d.ts: 50-54)
     Depth16Unorm = 50,
     Depth24Plus = 51,
     Depth24PlusStencil8 = 52,
     Depth32Float = 53,
+    /// Requires the `depth32float-stencil8` feature
+    Depth32FloatStencil8 = 54,
+
+    // BC compressed formats, `texture-compression-bc` (values match .d.ts: 100-113)
+    Bc1RgbaUnorm = 100,
+    Bc1RgbaUnormSrgb = 101,
+    Bc2RgbaUnorm = 102,
+    Bc2RgbaUnormSrgb = 103,
+    Bc3RgbaUnorm = 104,
+    Bc3RgbaUnormSrgb = 105,
+    Bc4RUnorm = 106,
+    Bc4RSnorm = 107,
+    Bc5RgUnorm = 108,
+    Bc5RgSnorm = 109,
+    Bc6hRgbUfloat = 110,
+    Bc6hRgbFloat = 111,
+    Bc7RgbaUnorm = 112,
+    Bc7RgbaUnormSrgb = 113,
+
+    // ETC2/EAC compressed formats, `texture-compression-etc2` (values match .d.ts: 120-129)
+    Etc2Rgb8Unorm = 120,
+    Etc2Rgb8UnormSrgb = 121,
+    Etc2Rgb8A1Unorm = 122,
+    Etc2Rgb8A1UnormSrgb = 123,
+    Etc2Rgba8Unorm = 124,
+    Etc2Rgba8UnormSrgb = 125,
+    EacR11Unorm = 126,
+    EacR11Snorm = 127,
+    EacRg11Unorm = 128,
+    EacRg11Snorm = 129,
+
+    // ASTC compressed formats, `texture-compression-astc` (values match .d.ts: 140-167)
+    Astc4x4Unorm = 140,
+    Astc4x4UnormSrgb = 141,
+    Astc5x4Unorm = 142,
+    Astc5x4UnormSrgb = 143,
+    Astc5x5Unorm = 144,
+    Astc5x5UnormSrgb = 145,
+    Astc6x5Unorm = 146,
+    Astc6x5UnormSrgb = 147,
+    Astc6x6Unorm = 148,
+    Astc6x6UnormSrgb = 149,
+    Astc8x5Unorm = 150,
+    Astc8x5UnormSrgb = 151,
+    Astc8x6Unorm = 152,
+    Astc8x6UnormSrgb = 153,
+    Astc8x8Unorm = 154,
+    Astc8x8UnormSrgb = 155,
+    Astc10x5Unorm = 156,
+    Astc10x5UnormSrgb = 157,
+    Astc10x6Unorm = 158,
+    Astc10x6UnormSrgb = 159,
+    Astc10x8Unorm = 160,
+    Astc10x8UnormSrgb = 161,
+    Astc10x10Unorm = 162,
+    Astc10x10UnormSrgb = 163,
+    Astc12x10Unorm = 164,
+    Astc12x10UnormSrgb = 165,
+    Astc12x12Unorm = 166,
+    Astc12x12UnormSrgb = 167,
 }
 
 impl WTextureFormat {
     pub(crate) fn to_wgpu(self) -> wgpu::TextureFormat {
+        let astc = |block, channel| wgpu::TextureFormat::Astc { block, channel };
         match self {
             Self::R8Unorm => wgpu::TextureFormat::R8Unorm,
             Self::R8Snorm => wgpu::TextureFormat::R8Snorm,
             Self::R8Uint => wgpu::TextureFormat::R8Uint,
             Self::R8Sint => wgpu::TextureFormat::R8Sint,
+            Self::R16Uint => wgpu::TextureFormat::R16Uint,
+            Self::R16Sint => wgpu::TextureFormat::R16Sint,
+            Self::R16Float => wgpu::TextureFormat::R16Float,
+            Self::R32Uint => wgpu::TextureFormat::R32Uint,
+            Self::R32Sint => wgpu::TextureFormat::R32Sint,
+            Self::R32Float => wgpu::TextureFormat::R32Float,
             Self::Rg8Unorm => wgpu::TextureFormat::Rg8Unorm,
             Self::Rg8Snorm => wgpu::TextureFormat::Rg8Snorm,
             Self::Rg8Uint => wgpu::TextureFormat::Rg8Uint,
             Self::Rg8Sint => wgpu::TextureFormat::Rg8Sint,
+            Self::Rg16Uint => wgpu::TextureFormat::Rg16Uint,
+            Self::Rg16Sint => wgpu::TextureFormat::Rg16Sint,
+            Self::Rg16Float => wgpu::TextureFormat::Rg16Float,
+            Self::Rg32Uint => wgpu::TextureFormat::Rg32Uint,
+            Self::Rg32Sint => wgpu::TextureFormat::Rg32Sint,
+            Self::Rg32Float => wgpu::TextureFormat::Rg32Float,
             Self::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
             Self::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
             Self::Rgba8Snorm => wgpu::TextureFormat::Rgba8Snorm,
@@ -56,12 +166,221 @@ impl WTextureFormat {
             Self::Rgba8Sint => wgpu::TextureFormat::Rgba8Sint,
             Self::Bgra8Unorm => wgpu::TextureFormat::Bgra8Unorm,
             Self::Bgra8UnormSrgb => wgpu::TextureFormat::Bgra8UnormSrgb,
+            Self::Rgb10a2Unorm => wgpu::TextureFormat::Rgb10a2Unorm,
+            Self::Rgb10a2Uint => wgpu::TextureFormat::Rgb10a2Uint,
+            Self::Rg11b10Ufloat => wgpu::TextureFormat::Rg11b10Ufloat,
+            Self::Rgba16Uint => wgpu::TextureFormat::Rgba16Uint,
+            Self::Rgba16Sint => wgpu::TextureFormat::Rgba16Sint,
+            Self::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            Self::Rgba32Uint => wgpu::TextureFormat::Rgba32Uint,
+            Self::Rgba32Sint => wgpu::TextureFormat::Rgba32Sint,
+            Self::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            Self::Rgb9e5Ufloat => wgpu::TextureFormat::Rgb9e5Ufloat,
             Self::Depth16Unorm => wgpu::TextureFormat::Depth16Unorm,
             Self::Depth24Plus => wgpu::TextureFormat::Depth24Plus,
             Self::Depth24PlusStencil8 => wgpu::TextureFormat::Depth24PlusStencil8,
             Self::Depth32Float => wgpu::TextureFormat::Depth32Float,
+            Self::Depth32FloatStencil8 => wgpu::TextureFormat::Depth32FloatStencil8,
+            Self::Bc1RgbaUnorm => wgpu::TextureFormat::Bc1RgbaUnorm,
+            Self::Bc1RgbaUnormSrgb => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            Self::Bc2RgbaUnorm => wgpu::TextureFormat::Bc2RgbaUnorm,
+            Self::Bc2RgbaUnormSrgb => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+            Self::Bc3RgbaUnorm => wgpu::TextureFormat::Bc3RgbaUnorm,
+            Self::Bc3RgbaUnormSrgb => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            Self::Bc4RUnorm => wgpu::TextureFormat::Bc4RUnorm,
+            Self::Bc4RSnorm => wgpu::TextureFormat::Bc4RSnorm,
+            Self::Bc5RgUnorm => wgpu::TextureFormat::Bc5RgUnorm,
+            Self::Bc5RgSnorm => wgpu::TextureFormat::Bc5RgSnorm,
+            Self::Bc6hRgbUfloat => wgpu::TextureFormat::Bc6hRgbUfloat,
+            Self::Bc6hRgbFloat => wgpu::TextureFormat::Bc6hRgbFloat,
+            Self::Bc7RgbaUnorm => wgpu::TextureFormat::Bc7RgbaUnorm,
+            Self::Bc7RgbaUnormSrgb => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+            Self::Etc2Rgb8Unorm => wgpu::TextureFormat::Etc2Rgb8Unorm,
+            Self::Etc2Rgb8UnormSrgb => wgpu::TextureFormat::Etc2Rgb8UnormSrgb,
+            Self::Etc2Rgb8A1Unorm => wgpu::TextureFormat::Etc2Rgb8A1Unorm,
+            Self::Etc2Rgb8A1UnormSrgb => wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb,
+            Self::Etc2Rgba8Unorm => wgpu::TextureFormat::Etc2Rgba8Unorm,
+            Self::Etc2Rgba8UnormSrgb => wgpu::TextureFormat::Etc2Rgba8UnormSrgb,
+            Self::EacR11Unorm => wgpu::TextureFormat::EacR11Unorm,
+            Self::EacR11Snorm => wgpu::TextureFormat::EacR11Snorm,
+            Self::EacRg11Unorm => wgpu::TextureFormat::EacRg11Unorm,
+            Self::EacRg11Snorm => wgpu::TextureFormat::EacRg11Snorm,
+            Self::Astc4x4Unorm => astc(wgpu::AstcBlock::B4x4, wgpu::AstcChannel::Unorm),
+            Self::Astc4x4UnormSrgb => astc(wgpu::AstcBlock::B4x4, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc5x4Unorm => astc(wgpu::AstcBlock::B5x4, wgpu::AstcChannel::Unorm),
+            Self::Astc5x4UnormSrgb => astc(wgpu::AstcBlock::B5x4, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc5x5Unorm => astc(wgpu::AstcBlock::B5x5, wgpu::AstcChannel::Unorm),
+            Self::Astc5x5UnormSrgb => astc(wgpu::AstcBlock::B5x5, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc6x5Unorm => astc(wgpu::AstcBlock::B6x5, wgpu::AstcChannel::Unorm),
+            Self::Astc6x5UnormSrgb => astc(wgpu::AstcBlock::B6x5, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc6x6Unorm => astc(wgpu::AstcBlock::B6x6, wgpu::AstcChannel::Unorm),
+            Self::Astc6x6UnormSrgb => astc(wgpu::AstcBlock::B6x6, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc8x5Unorm => astc(wgpu::AstcBlock::B8x5, wgpu::AstcChannel::Unorm),
+            Self::Astc8x5UnormSrgb => astc(wgpu::AstcBlock::B8x5, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc8x6Unorm => astc(wgpu::AstcBlock::B8x6, wgpu::AstcChannel::Unorm),
+            Self::Astc8x6UnormSrgb => astc(wgpu::AstcBlock::B8x6, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc8x8Unorm => astc(wgpu::AstcBlock::B8x8, wgpu::AstcChannel::Unorm),
+            Self::Astc8x8UnormSrgb => astc(wgpu::AstcBlock::B8x8, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc10x5Unorm => astc(wgpu::AstcBlock::B10x5, wgpu::AstcChannel::Unorm),
+            Self::Astc10x5UnormSrgb => astc(wgpu::AstcBlock::B10x5, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc10x6Unorm => astc(wgpu::AstcBlock::B10x6, wgpu::AstcChannel::Unorm),
+            Self::Astc10x6UnormSrgb => astc(wgpu::AstcBlock::B10x6, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc10x8Unorm => astc(wgpu::AstcBlock::B10x8, wgpu::AstcChannel::Unorm),
+            Self::Astc10x8UnormSrgb => astc(wgpu::AstcBlock::B10x8, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc10x10Unorm => astc(wgpu::AstcBlock::B10x10, wgpu::AstcChannel::Unorm),
+            Self::Astc10x10UnormSrgb => astc(wgpu::AstcBlock::B10x10, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc12x10Unorm => astc(wgpu::AstcBlock::B12x10, wgpu::AstcChannel::Unorm),
+            Self::Astc12x10UnormSrgb => astc(wgpu::AstcBlock::B12x10, wgpu::AstcChannel::UnormSrgb),
+            Self::Astc12x12Unorm => astc(wgpu::AstcBlock::B12x12, wgpu::AstcChannel::Unorm),
+            Self::Astc12x12UnormSrgb => astc(wgpu::AstcBlock::B12x12, wgpu::AstcChannel::UnormSrgb),
         }
     }
+
+    /// Map a wgpu format back to WTextureFormat (None if we don't expose it)
+    pub(crate) fn from_wgpu(format: wgpu::TextureFormat) -> Option<Self> {
+        Some(match format {
+            wgpu::TextureFormat::R8Unorm => Self::R8Unorm,
+            wgpu::TextureFormat::R8Snorm => Self::R8Snorm,
+            wgpu::TextureFormat::R8Uint => Self::R8Uint,
+            wgpu::TextureFormat::R8Sint => Self::R8Sint,
+            wgpu::TextureFormat::R16Uint => Self::R16Uint,
+            wgpu::TextureFormat::R16Sint => Self::R16Sint,
+            wgpu::TextureFormat::R16Float => Self::R16Float,
+            wgpu::TextureFormat::R32Uint => Self::R32Uint,
+            wgpu::TextureFormat::R32Sint => Self::R32Sint,
+            wgpu::TextureFormat::R32Float => Self::R32Float,
+            wgpu::TextureFormat::Rg8Unorm => Self::Rg8Unorm,
+            wgpu::TextureFormat::Rg8Snorm => Self::Rg8Snorm,
+            wgpu::TextureFormat::Rg8Uint => Self::Rg8Uint,
+            wgpu::TextureFormat::Rg8Sint => Self::Rg8Sint,
+            wgpu::TextureFormat::Rg16Uint => Self::Rg16Uint,
+            wgpu::TextureFormat::Rg16Sint => Self::Rg16Sint,
+            wgpu::TextureFormat::Rg16Float => Self::Rg16Float,
+            wgpu::TextureFormat::Rg32Uint => Self::Rg32Uint,
+            wgpu::TextureFormat::Rg32Sint => Self::Rg32Sint,
+            wgpu::TextureFormat::Rg32Float => Self::Rg32Float,
+            wgpu::TextureFormat::Rgba8Unorm => Self::Rgba8Unorm,
+            wgpu::TextureFormat::Rgba8UnormSrgb => Self::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba8Snorm => Self::Rgba8Snorm,
+            wgpu::TextureFormat::Rgba8Uint => Self::Rgba8Uint,
+            wgpu::TextureFormat::Rgba8Sint => Self::Rgba8Sint,
+            wgpu::TextureFormat::Bgra8Unorm => Self::Bgra8Unorm,
+            wgpu::TextureFormat::Bgra8UnormSrgb => Self::Bgra8UnormSrgb,
+            wgpu::TextureFormat::Rgb10a2Unorm => Self::Rgb10a2Unorm,
+            wgpu::TextureFormat::Rgb10a2Uint => Self::Rgb10a2Uint,
+            wgpu::TextureFormat::Rg11b10Ufloat => Self::Rg11b10Ufloat,
+            wgpu::TextureFormat::Rgba16Uint => Self::Rgba16Uint,
+            wgpu::TextureFormat::Rgba16Sint => Self::Rgba16Sint,
+            wgpu::TextureFormat::Rgba16Float => Self::Rgba16Float,
+            wgpu::TextureFormat::Rgba32Uint => Self::Rgba32Uint,
+            wgpu::TextureFormat::Rgba32Sint => Self::Rgba32Sint,
+            wgpu::TextureFormat::Rgba32Float => Self::Rgba32Float,
+            wgpu::TextureFormat::Rgb9e5Ufloat => Self::Rgb9e5Ufloat,
+            wgpu::TextureFormat::Depth16Unorm => Self::Depth16Unorm,
+            wgpu::TextureFormat::Depth24Plus => Self::Depth24Plus,
+            wgpu::TextureFormat::Depth24PlusStencil8 => Self::Depth24PlusStencil8,
+            wgpu::TextureFormat::Depth32Float => Self::Depth32Float,
+            wgpu::TextureFormat::Depth32FloatStencil8 => Self::Depth32FloatStencil8,
+            wgpu::TextureFormat::Bc1RgbaUnorm => Self::Bc1RgbaUnorm,
+            wgpu::TextureFormat::Bc1RgbaUnormSrgb => Self::Bc1RgbaUnormSrgb,
+            wgpu::TextureFormat::Bc2RgbaUnorm => Self::Bc2RgbaUnorm,
+            wgpu::TextureFormat::Bc2RgbaUnormSrgb => Self::Bc2RgbaUnormSrgb,
+            wgpu::TextureFormat::Bc3RgbaUnorm => Self::Bc3RgbaUnorm,
+            wgpu::TextureFormat::Bc3RgbaUnormSrgb => Self::Bc3RgbaUnormSrgb,
+            wgpu::TextureFormat::Bc4RUnorm => Self::Bc4RUnorm,
+            wgpu::TextureFormat::Bc4RSnorm => Self::Bc4RSnorm,
+            wgpu::TextureFormat::Bc5RgUnorm => Self::Bc5RgUnorm,
+            wgpu::TextureFormat::Bc5RgSnorm => Self::Bc5RgSnorm,
+            wgpu::TextureFormat::Bc6hRgbUfloat => Self::Bc6hRgbUfloat,
+            wgpu::TextureFormat::Bc6hRgbFloat => Self::Bc6hRgbFloat,
+            wgpu::TextureFormat::Bc7RgbaUnorm => Self::Bc7RgbaUnorm,
+            wgpu::TextureFormat::Bc7RgbaUnormSrgb => Self::Bc7RgbaUnormSrgb,
+            wgpu::TextureFormat::Etc2Rgb8Unorm => Self::Etc2Rgb8Unorm,
+            wgpu::TextureFormat::Etc2Rgb8UnormSrgb => Self::Etc2Rgb8UnormSrgb,
+            wgpu::TextureFormat::Etc2Rgb8A1Unorm => Self::Etc2Rgb8A1Unorm,
+            wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb => Self::Etc2Rgb8A1UnormSrgb,
+            wgpu::TextureFormat::Etc2Rgba8Unorm => Self::Etc2Rgba8Unorm,
+            wgpu::TextureFormat::Etc2Rgba8UnormSrgb => Self::Etc2Rgba8UnormSrgb,
+            wgpu::TextureFormat::EacR11Unorm => Self::EacR11Unorm,
+            wgpu::TextureFormat::EacR11Snorm => Self::EacR11Snorm,
+            wgpu::TextureFormat::EacRg11Unorm => Self::EacRg11Unorm,
+            wgpu::TextureFormat::EacRg11Snorm => Self::EacRg11Snorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B4x4, channel: wgpu::AstcChannel::Unorm } => Self::Astc4x4Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B4x4, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc4x4UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B5x4, channel: wgpu::AstcChannel::Unorm } => Self::Astc5x4Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B5x4, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc5x4UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B5x5, channel: wgpu::AstcChannel::Unorm } => Self::Astc5x5Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B5x5, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc5x5UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B6x5, channel: wgpu::AstcChannel::Unorm } => Self::Astc6x5Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B6x5, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc6x5UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B6x6, channel: wgpu::AstcChannel::Unorm } => Self::Astc6x6Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B6x6, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc6x6UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x5, channel: wgpu::AstcChannel::Unorm } => Self::Astc8x5Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x5, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc8x5UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x6, channel: wgpu::AstcChannel::Unorm } => Self::Astc8x6Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x6, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc8x6UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x8, channel: wgpu::AstcChannel::Unorm } => Self::Astc8x8Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B8x8, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc8x8UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x5, channel: wgpu::AstcChannel::Unorm } => Self::Astc10x5Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x5, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc10x5UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x6, channel: wgpu::AstcChannel::Unorm } => Self::Astc10x6Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x6, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc10x6UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x8, channel: wgpu::AstcChannel::Unorm } => Self::Astc10x8Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x8, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc10x8UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x10, channel: wgpu::AstcChannel::Unorm } => Self::Astc10x10Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B10x10, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc10x10UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B12x10, channel: wgpu::AstcChannel::Unorm } => Self::Astc12x10Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B12x10, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc12x10UnormSrgb,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B12x12, channel: wgpu::AstcChannel::Unorm } => Self::Astc12x12Unorm,
+            wgpu::TextureFormat::Astc { block: wgpu::AstcBlock::B12x12, channel: wgpu::AstcChannel::UnormSrgb } => Self::Astc12x12UnormSrgb,
+            _ => return None,
+        })
+    }
+
+    /// Whether this is a depth or depth-stencil format
+    pub(crate) fn is_depth(self) -> bool {
+        matches!(
+            self,
+            Self::Depth16Unorm
+                | Self::Depth24Plus
+                | Self::Depth24PlusStencil8
+                | Self::Depth32Float
+                | Self::Depth32FloatStencil8
+        )
+    }
+}
+
+/// Which aspect of a texture a view or write covers (values match .d.ts)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WTextureAspect {
+    All = 0,
+    StencilOnly = 1,
+    DepthOnly = 2,
+}
+
+impl WTextureAspect {
+    pub(crate) fn to_wgpu(self) -> wgpu::TextureAspect {
+        match self {
+            Self::All => wgpu::TextureAspect::All,
+            Self::StencilOnly => wgpu::TextureAspect::StencilOnly,
+            Self::DepthOnly => wgpu::TextureAspect::DepthOnly,
+        }
+    }
+
+    /// Fail with a clear error if `format` has no such aspect
+    pub(crate) fn check_format(self, format: WTextureFormat, what: &str) -> Result<(), JsValue> {
+        let wgpu_format = format.to_wgpu();
+        let ok = match self {
+            Self::All => true,
+            Self::DepthOnly => wgpu_format.has_depth_aspect(),
+            Self::StencilOnly => wgpu_format.has_stencil_aspect(),
+        };
+        if !ok {
+            return Err(JsValue::from_str(&format!("{}: {:?} has no {:?} aspect", what, format, self)));
+        }
+        Ok(())
+    }
 }
 
 /// Texture dimension
@@ -96,6 +415,35 @@ pub enum WTextureViewDimension {
 }
 
 impl WTextureViewDimension {
+    /// Default view dimension for a texture, matching how the GL backend creates it
+    ///
+    /// The GL backend makes every square 2D texture whose layer count is a
+    /// multiple of 6 a cube map (array), and such textures can only be sampled
+    /// as one, so those default to Cube (or CubeArray where supported, which
+    /// WebGL2 is not) rather than D2Array.
+    pub(crate) fn infer(dimension: WTextureDimension, width: u32, height: u32, layers: u32, cube_arrays: bool) -> Self {
+        match dimension {
+            WTextureDimension::D1 => Self::D1,
+            WTextureDimension::D3 => Self::D3,
+            WTextureDimension::D2 if layers == 6 && width == height => Self::Cube,
+            WTextureDimension::D2 if cube_arrays && layers.is_multiple_of(6) && width == height => Self::CubeArray,
+            WTextureDimension::D2 if layers > 1 => Self::D2Array,
+            WTextureDimension::D2 => Self::D2,
+        }
+    }
+
+    /// Whether views of this dimension can be made of a texture with the given shape
+    pub(crate) fn is_compatible(self, dimension: WTextureDimension, width: u32, height: u32, layers: u32) -> bool {
+        match (self, dimension) {
+            (Self::D1, WTextureDimension::D1) | (Self::D3, WTextureDimension::D3) => true,
+            (Self::D2, WTextureDimension::D2) => layers == 1,
+            (Self::D2Array, WTextureDimension::D2) => true,
+            (Self::Cube, WTextureDimension::D2) => layers == 6 && width == height,
+            (Self::CubeArray, WTextureDimension::D2) => layers.is_multiple_of(6) && width == height,
+            _ => false,
+        }
+    }
+
     pub(crate) fn to_wgpu(self) -> wgpu::TextureViewDimension {
         match self {
             Self::D1 => wgpu::TextureViewDimension::D1,
@@ -116,14 +464,280 @@ pub struct WTexture {
     pub(crate) width: u32,
     pub(crate) height: u32,
     pub(crate) depth_or_array_layers: u32,
+    pub(crate) dimension: WTextureDimension,
+    /// Dimension of views made by `createView` (the `textureBindingViewDimension`)
+    pub(crate) view_dimension: WTextureViewDimension,
     pub(crate) format: WTextureFormat,
     pub(crate) mip_level_count: u32,
+    // Shared with views, bind groups and recorded commands referencing this texture
+    pub(crate) lifetime: Rc<ResourceState>,
 }
 
 impl WTexture {
     pub(crate) fn inner(&self) -> Option<&wgpu::Texture> {
         self.inner.as_ref()
     }
+
+    /// Fail with a clear error if the texture has been destroyed
+    pub(crate) fn check_not_destroyed(&self, what: &str) -> Result<(), JsValue> {
+        self.lifetime.check_not_destroyed(what)
+    }
+
+    /// Check an aspect for a view and get the format the view will have
+    ///
+    /// A depth-only view of a depth-stencil texture has the depth format (e.g.
+    /// Depth24Plus). WebGL2 has no DEPTH_STENCIL_TEXTURE_MODE (GLES 3.1), so a
+    /// stencil-only view could never be sampled and is rejected.
+    fn view_aspect_format(&self, aspect: WTextureAspect, what: &str) -> Result<WTextureFormat, JsValue> {
+        aspect.check_format(self.format, what)?;
+        if aspect == WTextureAspect::StencilOnly {
+            return Err(JsValue::from_str(&format!(
+                "{}: stencil-only views are not supported on WebGL2 (sampling stencil needs GLES 3.1)",
+                what
+            )));
+        }
+        Ok(self
+            .format
+            .to_wgpu()
+            .aspect_specific_format(aspect.to_wgpu())
+            .and_then(WTextureFormat::from_wgpu)
+            .unwrap_or(self.format))
+    }
+
+    /// Copy extent for a write of `width` x `height` x `depth` texels at `origin`
+    ///
+    /// Compressed formats are written in whole blocks, so the origin must be
+    /// block-aligned. A region reaching the edge of a mip level whose size is not
+    /// a multiple of the block size is rounded up to the level's physical size.
+    pub(crate) fn write_extent(
+        &self,
+        texture: &wgpu::Texture,
+        mip_level: u32,
+        origin: wgpu::Origin3d,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Result<wgpu::Extent3d, JsValue> {
+        let extent = wgpu::Extent3d { width, height, depth_or_array_layers: depth };
+        let (block_width, block_height) = texture.format().block_dimensions();
+        if block_width == 1 && block_height == 1 {
+            return Ok(extent);
+        }
+
+        let mip_size = texture.size().mip_level_size(mip_level, texture.dimension());
+        let align = |origin: u32, size: u32, block: u32, mip_size: u32| -> Option<u32> {
+            if !origin.is_multiple_of(block) {
+                return None;
+            }
+            if size.is_multiple_of(block) {
+                Some(size)
+            } else if origin + size == mip_size {
+                Some(size.next_multiple_of(block))
+            } else {
+                None
+            }
+        };
+        match (
+            align(origin.x, width, block_width, mip_size.width),
+            align(origin.y, height, block_height, mip_size.height),
+        ) {
+            (Some(width), Some(height)) => Ok(wgpu::Extent3d { width, height, depth_or_array_layers: depth }),
+            _ => Err(JsValue::from_str(&format!(
+                "writeTexture region {}x{} at ({}, {}) of mip {} ({}x{}) is not aligned to the {}x{} blocks of {:?}",
+                width, height, origin.x, origin.y, mip_level, mip_size.width, mip_size.height,
+                block_width, block_height, self.format
+            ))),
+        }
+    }
+
+    /// Write `data` to the texture region described by `upload`
+    ///
+    /// Shared by every texture write entry point. Checks the source layout
+    /// against `data` up front so a short buffer fails with a clear message,
+    /// then leaves the remaining validation to wgpu.
+    pub(crate) fn upload(&self, state: &DeviceState, data: &[u8], upload: &TextureUpload) -> Result<(), JsValue> {
+        self.check_not_destroyed("writeTexture")?;
+        upload.aspect.check_format(self.format, "writeTexture")?;
+        let Some(tex) = self.inner() else {
+            log::warn!("Cannot write to surface texture");
+            return Ok(());
+        };
+
+        let extent = self.write_extent(tex, upload.mip_level, upload.origin, upload.width, upload.height, upload.depth)?;
+        let format = tex.format();
+        let Some(block_size) = format.block_copy_size(Some(upload.aspect.to_wgpu())) else {
+            return Err(JsValue::from_str(&format!(
+                "writeTexture: {:?} has no single copy layout, write the depth or stencil aspect separately",
+                self.format
+            )));
+        };
+        let (block_width, block_height) = format.block_dimensions();
+        let conversion = upload.conversion;
+        if conversion != 0 {
+            check_conversion(self.format, upload.aspect, conversion)?;
+        }
+        // Size of one source texel (or block), before conversion
+        let source_block_size = if conversion & upload_conversion::RGB_TO_RGBA != 0 { 3 } else { block_size };
+        let row_bytes = extent.width / block_width * source_block_size;
+        let rows = extent.height / block_height;
+        let bytes_per_row = if upload.bytes_per_row == 0 { row_bytes } else { upload.bytes_per_row };
+        let rows_per_image = if upload.rows_per_image == 0 { rows } else { upload.rows_per_image };
+        if bytes_per_row < row_bytes || rows_per_image < rows {
+            return Err(JsValue::from_str(&format!(
+                "writeTexture: layout of {} bytes per row and {} rows per image is smaller than the {}x{} region ({} bytes per row, {} rows)",
+                bytes_per_row, rows_per_image, upload.width, upload.height, row_bytes, rows
+            )));
+        }
+
+        if upload.offset > data.len() as u64 {
+            return Err(JsValue::from_str(&format!(
+                "writeTexture data offset {} is past the end of the {}-byte data",
+                upload.offset,
+                data.len()
+            )));
+        }
+        let required = if extent.depth_or_array_layers == 0 || rows == 0 {
+            0
+        } else {
+            upload.offset
+                + bytes_per_row as u64 * rows_per_image as u64 * (extent.depth_or_array_layers - 1) as u64
+                + bytes_per_row as u64 * (rows - 1) as u64
+                + row_bytes as u64
+        };
+        if required > data.len() as u64 {
+            return Err(JsValue::from_str(&format!(
+                "writeTexture needs {} bytes from offset {} for {}x{}x{}, got {}",
+                required - upload.offset, upload.offset, upload.width, upload.height, upload.depth,
+                data.len()
+            )));
+        }
+
+        // Converted data is tightly packed from offset 0
+        let converted;
+        let (data, layout) = if conversion == 0 {
+            let layout = wgpu::TexelCopyBufferLayout {
+                offset: upload.offset,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(rows_per_image),
+            };
+            (data, layout)
+        } else {
+            let source = &data[upload.offset as usize..];
+            converted = convert_texels(source, extent, bytes_per_row, rows_per_image, conversion);
+            let layout = wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(extent.width * 4),
+                rows_per_image: Some(extent.height),
+            };
+            (converted.as_slice(), layout)
+        };
+
+        capture_error(&state.device, "writeTexture", || {
+            state.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: tex,
+                    mip_level: upload.mip_level,
+                    origin: upload.origin,
+                    aspect: upload.aspect.to_wgpu(),
+                },
+                data,
+                layout,
+                extent,
+            )
+        })?;
+
+        log::debug!(
+            "Wrote texture data: {}x{}x{} at ({}, {}, {}) of mip {}",
+            upload.width, upload.height, upload.depth,
+            upload.origin.x, upload.origin.y, upload.origin.z, upload.mip_level
+        );
+        Ok(())
+    }
+}
+
+/// Destination and source layout of a texture write
+///
+/// `bytes_per_row` and `rows_per_image` of 0 mean tightly packed. For 2D
+/// arrays and cubes `origin.z` is the first layer and `depth` the layer count.
+pub(crate) struct TextureUpload {
+    pub mip_level: u32,
+    pub origin: wgpu::Origin3d,
+    pub aspect: WTextureAspect,
+    /// Byte offset of the region in the source data
+    pub offset: u64,
+    pub bytes_per_row: u32,
+    pub rows_per_image: u32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// `upload_conversion` flags
+    pub conversion: u32,
+}
+
+/// Fail with a clear error if `conversion` can't be applied to writes of `format`
+fn check_conversion(format: WTextureFormat, aspect: WTextureAspect, conversion: u32) -> Result<(), JsValue> {
+    if conversion & !upload_conversion::ALL != 0 {
+        return Err(JsValue::from_str(&format!("writeTexture: unknown conversion flags {:#x}", conversion)));
+    }
+    let unorm = matches!(
+        format,
+        WTextureFormat::Rgba8Unorm
+            | WTextureFormat::Rgba8UnormSrgb
+            | WTextureFormat::Bgra8Unorm
+            | WTextureFormat::Bgra8UnormSrgb
+    );
+    let rgba8 = unorm || matches!(format, WTextureFormat::Rgba8Snorm | WTextureFormat::Rgba8Uint | WTextureFormat::Rgba8Sint);
+    if !rgba8 || aspect != WTextureAspect::All {
+        return Err(JsValue::from_str(&format!(
+            "writeTexture: conversions need an 8-bit RGBA/BGRA texture, not {:?}",
+            format
+        )));
+    }
+    if conversion & upload_conversion::PREMULTIPLY_ALPHA != 0 && !unorm {
+        return Err(JsValue::from_str(&format!(
+            "writeTexture: PREMULTIPLY_ALPHA needs a unorm format, not {:?}",
+            format
+        )));
+    }
+    Ok(())
+}
+
+/// Convert source texels to tightly packed 4-byte texels
+///
+/// `source` starts at the first texel of the region and uses the caller's row
+/// and image strides. Premultiplying works on the stored values, so for sRGB
+/// formats it happens in sRGB space (as browsers do for images).
+fn convert_texels(
+    source: &[u8],
+    extent: wgpu::Extent3d,
+    bytes_per_row: u32,
+    rows_per_image: u32,
+    conversion: u32,
+) -> Vec<u8> {
+    let texel_size = if conversion & upload_conversion::RGB_TO_RGBA != 0 { 3 } else { 4 };
+    let (width, height) = (extent.width as usize, extent.height as usize);
+    let mut converted = Vec::with_capacity(width * height * extent.depth_or_array_layers as usize * 4);
+
+    for image in 0..extent.depth_or_array_layers as usize {
+        for row in 0..height {
+            let source_row = if conversion & upload_conversion::FLIP_Y != 0 { height - 1 - row } else { row };
+            let start = (image * rows_per_image as usize + source_row) * bytes_per_row as usize;
+            for texel in source[start..start + width * texel_size].chunks_exact(texel_size) {
+                let mut rgba = [texel[0], texel[1], texel[2], if texel_size == 4 { texel[3] } else { 255 }];
+                if conversion & upload_conversion::SWAP_RED_BLUE != 0 {
+                    rgba.swap(0, 2);
+                }
+                if conversion & upload_conversion::PREMULTIPLY_ALPHA != 0 {
+                    let alpha = rgba[3] as u32;
+                    for channel in &mut rgba[..3] {
+                        *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+                    }
+                }
+                converted.extend_from_slice(&rgba);
+            }
+        }
+    }
+    converted
 }
 
 impl Drop for WTexture {
@@ -154,10 +768,44 @@ impl WTexture {
         self.format
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn dimension(&self) -> WTextureDimension {
+        self.dimension
+    }
+
+    /// Dimension of the views made by `createView`
+    #[wasm_bindgen(getter, js_name = viewDimension)]
+    pub fn view_dimension(&self) -> WTextureViewDimension {
+        self.view_dimension
+    }
+
+    /// Release the texture's GPU memory
+    ///
+    /// The handle stays valid, but any later use of the texture or its views
+    /// fails. If recorded but unsubmitted commands still use the texture, the
+    /// memory is released after they execute. Destroying the surface texture or
+    /// destroying twice is a no-op.
+    pub fn destroy(&self) {
+        let Some(ref texture) = self.inner else {
+            return;
+        };
+        if !self.lifetime.destroy(GpuResource::Texture(texture.clone())) {
+            return;
+        }
+        log::debug!("Destroyed texture {}x{}x{}", self.width, self.height, self.depth_or_array_layers);
+    }
+
+    /// Create a view of the whole texture with its `viewDimension`
+    ///
+    /// `aspect` defaults to All; use DepthOnly to sample the depth of a
+    /// depth-stencil texture.
     #[wasm_bindgen(js_name = createView)]
-    pub fn create_view(&self) -> WTextureView {
+    pub fn create_view(&self, aspect: Option<WTextureAspect>) -> Result<WTextureView, JsValue> {
+        self.check_not_destroyed("createView")?;
+        let aspect = aspect.unwrap_or(WTextureAspect::All);
+        let view_format = self.view_aspect_format(aspect, "createView")?;
         TEXTURE_VIEW_COUNT.fetch_add(1, Ordering::Relaxed);
-        if self.is_surface {
+        Ok(if self.is_surface {
             // For surface textures, we need to get the current frame
             WTextureView {
                 inner: None,
@@ -166,22 +814,17 @@ impl WTexture {
                 height: self.height,
                 format: self.format,
                 dimension: WTextureViewDimension::D2,
+                texture_lifetime: self.lifetime.clone(),
             }
         } else if let Some(ref texture) = self.inner {
-            // Explicitly set the dimension to avoid wgpu's heuristics
-            // (e.g., 12 layers would be assumed CubeArray because 12 % 6 == 0)
-            let view_dimension = if self.depth_or_array_layers > 1 {
-                wgpu::TextureViewDimension::D2Array
-            } else {
-                wgpu::TextureViewDimension::D2
-            };
-
+            // Use the dimension chosen at creation rather than wgpu's defaults
+            // (which would make a 6-layer array texture a D2Array view on any shape)
             let view = texture.create_view(&wgpu::TextureViewDescriptor {
                 label: None,
                 format: None,
-                dimension: Some(view_dimension),
+                dimension: Some(self.view_dimension.to_wgpu()),
                 usage: None,
-                aspect: wgpu::TextureAspect::All,
+                aspect: aspect.to_wgpu(),
                 base_mip_level: 0,
                 mip_level_count: None,
                 base_array_layer: 0,
@@ -192,19 +835,18 @@ impl WTexture {
                 is_surface: false,
                 width: self.width,
                 height: self.height,
-                format: self.format,
-                dimension: if self.depth_or_array_layers > 1 {
-                    WTextureViewDimension::D2Array
-                } else {
-                    WTextureViewDimension::D2
-                },
+                format: view_format,
+                dimension: self.view_dimension,
+                texture_lifetime: self.lifetime.clone(),
             }
         } else {
             panic!("Cannot create view from null texture");
-        }
+        })
     }
 
     /// Create a texture view with descriptor parameters
+    ///
+    /// `aspect` defaults to All (see `createView`).
     #[wasm_bindgen(js_name = createViewWithDescriptor)]
     pub fn create_view_with_descriptor(
         &self,
@@ -214,9 +856,13 @@ impl WTexture {
         mip_level_count: u32,
         base_array_layer: u32,
         array_layer_count: u32,
-    ) -> WTextureView {
+        aspect: Option<WTextureAspect>,
+    ) -> Result<WTextureView, JsValue> {
+        self.check_not_destroyed("createViewWithDescriptor")?;
+        let aspect = aspect.unwrap_or(WTextureAspect::All);
+        let aspect_format = self.view_aspect_format(aspect, "createViewWithDescriptor")?;
         TEXTURE_VIEW_COUNT.fetch_add(1, Ordering::Relaxed);
-        if self.is_surface {
+        Ok(if self.is_surface {
             WTextureView {
                 inner: None,
                 is_surface: true,
@@ -224,11 +870,12 @@ impl WTexture {
                 height: self.height,
                 format,
                 dimension,
+                texture_lifetime: self.lifetime.clone(),
             }
         } else if let Some(ref texture) = self.inner {
-            // Only specify format if it differs from texture format
-            // Otherwise wgpu requires it in view_formats array
-            let view_format = if format == self.format {
+            // Only specify format if it differs from texture format (or the
+            // aspect's format), otherwise wgpu requires it in view_formats array
+            let view_format = if format == self.format || format == aspect_format {
                 None
             } else {
                 Some(format.to_wgpu())
@@ -239,7 +886,7 @@ impl WTexture {
                 format: view_format,
                 dimension: Some(dimension.to_wgpu()),
                 usage: None,
-                aspect: wgpu::TextureAspect::All,
+                aspect: aspect.to_wgpu(),
                 base_mip_level,
                 mip_level_count: if mip_level_count == 0 { None } else { Some(mip_level_count) },
                 base_array_layer,
@@ -250,12 +897,13 @@ impl WTexture {
                 is_surface: false,
                 width: self.width >> base_mip_level,
                 height: self.height >> base_mip_level,
-                format: if format == self.format { self.format } else { format },
+                format: if format == self.format { aspect_format } else { format },
                 dimension,
+                texture_lifetime: self.lifetime.clone(),
             }
         } else {
             panic!("Cannot create view from null texture");
-        }
+        })
     }
 }
 
@@ -268,12 +916,18 @@ pub struct WTextureView {
     pub(crate) height: u32,
     pub(crate) format: WTextureFormat,
     pub(crate) dimension: WTextureViewDimension,
+    pub(crate) texture_lifetime: Rc<ResourceState>,
 }
 
 impl WTextureView {
     pub(crate) fn inner(&self) -> Option<&wgpu::TextureView> {
         self.inner.as_ref()
     }
+
+    /// Fail with a clear error if the view's texture has been destroyed
+    pub(crate) fn check_not_destroyed(&self, what: &str) -> Result<(), JsValue> {
+        self.texture_lifetime.check_not_destroyed(what)
+    }
 }
 
 impl Drop for WTextureView {
@@ -291,6 +945,10 @@ impl WTextureView {
 }
 
 /// Create a texture
+///
+/// `texture_binding_view_dimension` sets the dimension of views made by
+/// `createView`, e.g. Cube for a 6-layer skybox. By default it follows the
+/// texture dimension and layer count (see `WTextureViewDimension::infer`).
 #[wasm_bindgen(js_name = createTexture)]
 pub fn create_texture(
     device: &WDevice,
@@ -302,27 +960,58 @@ pub fn create_texture(
     mip_level_count: u32,
     sample_count: u32,
     usage: u32,
+    texture_binding_view_dimension: Option<WTextureViewDimension>,
 ) -> Result<WTexture, JsValue> {
     let state = device.state();
     let state = state.borrow();
 
-    // Check for WebGL2 limitations with depth texture arrays
-    let is_depth_format = matches!(
-        format,
-        WTextureFormat::Depth16Unorm
-            | WTextureFormat::Depth24Plus
-            | WTextureFormat::Depth24PlusStencil8
-            | WTextureFormat::Depth32Float
-    );
+    let cube_arrays = state
+        .adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::CUBE_ARRAY_TEXTURES);
+    let view_dimension = match texture_binding_view_dimension {
+        Some(view_dimension) => {
+            if !view_dimension.is_compatible(dimension, width, height, depth_or_array_layers) {
+                return Err(JsValue::from_str(&format!(
+                    "{:?} views are not possible for a {:?} texture of {}x{}x{}",
+                    view_dimension, dimension, width, height, depth_or_array_layers
+                )));
+            }
+            if view_dimension == WTextureViewDimension::CubeArray && !cube_arrays {
+                return Err(JsValue::from_str("CubeArray views are not supported on this WebGL2 context"));
+            }
+            let inferred = WTextureViewDimension::infer(dimension, width, height, depth_or_array_layers, cube_arrays);
+            if view_dimension == WTextureViewDimension::D2Array && inferred != view_dimension {
+                log::warn!(
+                    "{}x{}x{} texture will be a cube map on WebGL2; D2Array views of it cannot be sampled",
+                    width, height, depth_or_array_layers
+                );
+            }
+            view_dimension
+        }
+        None => WTextureViewDimension::infer(dimension, width, height, depth_or_array_layers, cube_arrays),
+    };
+
+    let missing = format.to_wgpu().required_features().difference(state.device.features());
+    if !missing.is_empty() {
+        let names: Vec<String> = missing.iter_names().map(|(name, _)| flag_name_to_js(name)).collect();
+        return Err(JsValue::from_str(&format!(
+            "{:?} requires feature(s) not enabled on the device: {}",
+            format,
+            names.join(", ")
+        )));
+    }
 
-    if is_depth_format && depth_or_array_layers > 1 {
+    // Check for WebGL2 limitations with depth texture arrays
+    if format.is_depth() && depth_or_array_layers > 1 {
         log::warn!(
             "Creating depth texture array ({}x{}x{}, {:?}) - this may not be supported on WebGL2",
             width, height, depth_or_array_layers, format
         );
     }
 
-    let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+    let descriptor = wgpu::TextureDescriptor {
         label: None,
         size: wgpu::Extent3d {
             width,
@@ -335,11 +1024,12 @@ pub fn create_texture(
         format: format.to_wgpu(),
         usage: wgpu::TextureUsages::from_bits_truncate(usage),
         view_formats: &[],
-    });
+    };
+    let texture = capture_error(&state.device, "createTexture", || state.device.create_texture(&descriptor))?;
 
     log::debug!(
-        "Created texture: {}x{}x{}, format={:?}, mips={}, samples={}",
-        width, height, depth_or_array_layers, format, mip_level_count, sample_count
+        "Created texture: {}x{}x{}, format={:?}, view={:?}, mips={}, samples={}",
+        width, height, depth_or_array_layers, format, view_dimension, mip_level_count, sample_count
     );
 
     TEXTURE_COUNT.fetch_add(1, Ordering::Relaxed);
@@ -350,8 +1040,11 @@ pub fn create_texture(
         width,
         height,
         depth_or_array_layers,
+        dimension,
+        view_dimension,
         format,
         mip_level_count: mip_level_count.max(1),
+        lifetime: ResourceState::new("texture"),
     })
 }
 
@@ -360,7 +1053,9 @@ pub fn create_texture(
 #[wasm_bindgen(js_name = getSurfaceTexture)]
 pub fn get_surface_texture(device: &WDevice) -> WTexture {
     let state = device.state();
-    let state = state.borrow();
+    let mut state = state.borrow_mut();
+    // Start of a new frame: pick up any size change observed by auto resize
+    state.apply_pending_resize();
 
     TEXTURE_COUNT.fetch_add(1, Ordering::Relaxed);
 
@@ -370,12 +1065,20 @@ pub fn get_surface_texture(device: &WDevice) -> WTexture {
         width: state.surface_config.width,
         height: state.surface_config.height,
         depth_or_array_layers: 1,
-        format: WTextureFormat::Bgra8Unorm, // Will be overridden by actual surface format
+        dimension: WTextureDimension::D2,
+        view_dimension: WTextureViewDimension::D2,
+        format: state.surface_format(),
         mip_level_count: 1,
+        lifetime: ResourceState::new("texture"),
     }
 }
 
 /// Write data to a texture
+///
+/// Same as `queue.writeTexture`, with the region parameters after the layout so
+/// that a whole-texture write only needs the size: `mip_level`, the origin and
+/// `data_offset` default to 0. `conversion` takes the same flags (see
+/// `getUploadConversion()`).
 #[wasm_bindgen(js_name = writeTexture)]
 pub fn write_texture(
     queue: &WQueue,
@@ -386,33 +1089,79 @@ pub fn write_texture(
     width: u32,
     height: u32,
     depth: u32,
-) {
-    let state = queue.state();
-    let state = state.borrow();
+    aspect: Option<WTextureAspect>,
+    conversion: Option<u32>,
+    mip_level: Option<u32>,
+    origin_x: Option<u32>,
+    origin_y: Option<u32>,
+    origin_z: Option<u32>,
+    data_offset: Option<f64>,
+) -> Result<(), JsValue> {
+    queue.write_texture(
+        texture,
+        mip_level.unwrap_or(0),
+        origin_x.unwrap_or(0),
+        origin_y.unwrap_or(0),
+        origin_z.unwrap_or(0),
+        data,
+        bytes_per_row,
+        rows_per_image,
+        width,
+        height,
+        depth,
+        aspect,
+        data_offset,
+        conversion,
+    )
+}
 
-    if let Some(ref tex) = texture.inner {
-        state.queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(bytes_per_row),
-                rows_per_image: Some(rows_per_image),
-            },
-            wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: depth,
-            },
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32, depth: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d { width, height, depth_or_array_layers: depth }
+    }
+
+    #[test]
+    fn rgb_is_expanded_with_opaque_alpha() {
+        let rgb = [1, 2, 3, 4, 5, 6];
+        let rgba = convert_texels(&rgb, extent(2, 1, 1), 6, 1, upload_conversion::RGB_TO_RGBA);
+        assert_eq!(rgba, [1, 2, 3, 255, 4, 5, 6, 255]);
+    }
+
+    #[test]
+    fn red_and_blue_are_swapped() {
+        let bgra = [10, 20, 30, 40];
+        assert_eq!(convert_texels(&bgra, extent(1, 1, 1), 4, 1, upload_conversion::SWAP_RED_BLUE), [30, 20, 10, 40]);
+
+        let bgr = [10, 20, 30];
+        let conversion = upload_conversion::RGB_TO_RGBA | upload_conversion::SWAP_RED_BLUE;
+        assert_eq!(convert_texels(&bgr, extent(1, 1, 1), 3, 1, conversion), [30, 20, 10, 255]);
+    }
+
+    #[test]
+    fn premultiplying_rounds_to_nearest() {
+        let texels = [255, 128, 1, 128, 200, 100, 50, 0, 9, 8, 7, 255];
+        let converted = convert_texels(&texels, extent(3, 1, 1), 12, 1, upload_conversion::PREMULTIPLY_ALPHA);
+        assert_eq!(converted, [128, 64, 1, 128, 0, 0, 0, 0, 9, 8, 7, 255]);
+    }
+
+    #[test]
+    fn padded_rows_and_images_are_packed_and_flipped_per_image() {
+        // 1x2 texels per image, 2 images, 8 bytes per row and 3 rows per image
+        #[rustfmt::skip]
+        let source = [
+            1, 1, 1, 1, 0, 0, 0, 0,
+            2, 2, 2, 2, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            3, 3, 3, 3, 0, 0, 0, 0,
+            4, 4, 4, 4,
+        ];
+        let packed = convert_texels(&source, extent(1, 2, 2), 8, 3, upload_conversion::SWAP_RED_BLUE);
+        assert_eq!(packed, [1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4]);
 
-        log::debug!("Wrote texture data: {}x{}x{}", width, height, depth);
-    } else {
-        log::warn!("Cannot write to surface texture");
+        let flipped = convert_texels(&source, extent(1, 2, 2), 8, 3, upload_conversion::FLIP_Y);
+        assert_eq!(flipped, [2, 2, 2, 2, 1, 1, 1, 1, 4, 4, 4, 4, 3, 3, 3, 3]);
     }
 }
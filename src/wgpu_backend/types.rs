@@ -114,6 +114,61 @@ pub enum WStoreOp {
     Discard = 1,
 }
 
+/// Surface present mode
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WPresentMode {
+    AutoVsync = 0,
+    AutoNoVsync = 1,
+    Fifo = 2,
+    FifoRelaxed = 3,
+    Immediate = 4,
+    Mailbox = 5,
+}
+
+impl WPresentMode {
+    pub(crate) fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            Self::AutoVsync => wgpu::PresentMode::AutoVsync,
+            Self::AutoNoVsync => wgpu::PresentMode::AutoNoVsync,
+            Self::Fifo => wgpu::PresentMode::Fifo,
+            Self::FifoRelaxed => wgpu::PresentMode::FifoRelaxed,
+            Self::Immediate => wgpu::PresentMode::Immediate,
+            Self::Mailbox => wgpu::PresentMode::Mailbox,
+        }
+    }
+}
+
+/// Surface alpha compositing mode (how the canvas is composited with the page)
+///
+/// On WebGL2 this maps to the context's `alpha`/`premultipliedAlpha` attributes.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WCompositeAlphaMode {
+    Auto = 0,
+    Opaque = 1,
+    PreMultiplied = 2,
+    PostMultiplied = 3,
+    Inherit = 4,
+}
+
+/// Color space of pixels copied with `copyExternalImageToTexture`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WPredefinedColorSpace {
+    Srgb = 0,
+    DisplayP3 = 1,
+}
+
+impl WPredefinedColorSpace {
+    pub(crate) fn to_wgpu(self) -> wgpu::PredefinedColorSpace {
+        match self {
+            Self::Srgb => wgpu::PredefinedColorSpace::Srgb,
+            Self::DisplayP3 => wgpu::PredefinedColorSpace::DisplayP3,
+        }
+    }
+}
+
 /// Index format
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -245,6 +300,23 @@ pub mod texture_usage {
     pub const RENDER_ATTACHMENT: u32 = 16;
 }
 
+/// CPU-side conversions applied to `writeTexture` data (8-bit RGBA/BGRA textures)
+///
+/// Exposed to JS through `getUploadConversion()`.
+///
+/// Applied in this order: RGB_TO_RGBA, SWAP_RED_BLUE, PREMULTIPLY_ALPHA, FLIP_Y.
+pub mod upload_conversion {
+    /// Source has 3 bytes per texel; alpha is filled with 255
+    pub const RGB_TO_RGBA: u32 = 1;
+    /// Swap the first and third channels (BGRA <-> RGBA)
+    pub const SWAP_RED_BLUE: u32 = 2;
+    /// Multiply color by alpha (straight -> premultiplied)
+    pub const PREMULTIPLY_ALPHA: u32 = 4;
+    /// Reverse the row order of each image
+    pub const FLIP_Y: u32 = 8;
+    pub(crate) const ALL: u32 = RGB_TO_RGBA | SWAP_RED_BLUE | PREMULTIPLY_ALPHA | FLIP_Y;
+}
+
 /// Shader stage
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -382,3 +454,14 @@ pub fn get_buffer_usage() -> JsValue {
     js_sys::Reflect::set(&obj, &"STORAGE".into(), &buffer_usage::STORAGE.into()).unwrap();
     obj.into()
 }
+
+/// Get texture upload conversion flags for `writeTexture` (for JS access)
+#[wasm_bindgen(js_name = getUploadConversion)]
+pub fn get_upload_conversion() -> JsValue {
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(&obj, &"RGB_TO_RGBA".into(), &upload_conversion::RGB_TO_RGBA.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"SWAP_RED_BLUE".into(), &upload_conversion::SWAP_RED_BLUE.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"PREMULTIPLY_ALPHA".into(), &upload_conversion::PREMULTIPLY_ALPHA.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"FLIP_Y".into(), &upload_conversion::FLIP_Y.into()).unwrap();
+    obj.into()
+}